@@ -0,0 +1,243 @@
+pub mod activate {
+    use std::path::Path;
+
+    use serde::{Deserialize, Serialize};
+
+    use crate::envvar::environment_variable::memory::MemoryEnvironment;
+    use crate::format::format;
+    use crate::json::config::{self, Config};
+    use crate::portable::portable::Translation;
+    use crate::settings::settings::Settings;
+    use crate::shellrc::detect::resolve_session_shell;
+    use crate::shellrc::shellrc::{ShellRunCommandFile, ShellRunCommandFileData};
+
+    /// Holds the stack of activations in the environment of the shell session.
+    pub const STACK_KEY: &str = "ENVVAR_ACTIVATE";
+
+    /// The value of a variable before it was changed, `None` if it was not set.
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    pub struct SavedValue {
+        pub key: String,
+        pub value: Option<String>,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    pub struct Frame {
+        pub name: String,
+        pub saved: Vec<SavedValue>,
+    }
+
+    pub fn parse_stack(s: &str) -> Result<Vec<Frame>, String> {
+        if s.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        match serde_json::from_str::<Vec<Frame>>(s) {
+            Ok(f) => Ok(f),
+            Err(e) => Err(format!("invalid {}: {}", STACK_KEY, e)),
+        }
+    }
+
+    fn stack_to_string(stack: &Vec<Frame>) -> Result<String, String> {
+        match serde_json::to_string(stack) {
+            Ok(s) => Ok(s),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    /// Compares two environments and records the changes in `data`, returning the prior values.
+    pub fn record_changes(
+        before: &[(String, String)],
+        after: &[(String, String)],
+        data: &mut ShellRunCommandFileData,
+    ) -> Vec<SavedValue> {
+        let mut saved: Vec<SavedValue> = Vec::new();
+
+        for (k, v) in after.iter() {
+            let prior = before
+                .iter()
+                .find(|(bk, _)| bk == k)
+                .map(|(_, bv)| bv.to_string());
+            if prior.as_ref() == Some(v) {
+                continue;
+            }
+
+            data.add(k, v);
+            saved.push(SavedValue {
+                key: k.to_string(),
+                value: prior,
+            });
+        }
+
+        for (k, v) in before.iter() {
+            if after.iter().any(|(ak, _)| ak == k) {
+                continue;
+            }
+
+            data.unset(k);
+            saved.push(SavedValue {
+                key: k.to_string(),
+                value: Some(v.to_string()),
+            });
+        }
+
+        saved
+    }
+
+    /// Records in `data` the changes that restore the values saved in `frame`.
+    pub fn record_restore(frame: &Frame, data: &mut ShellRunCommandFileData) {
+        for s in frame.saved.iter() {
+            match &s.value {
+                Some(v) => data.add(&s.key, v),
+                None => data.unset(&s.key),
+            }
+        }
+    }
+
+    /// Builds the commands that apply `config` on top of `before` and push a new activation.
+    pub fn build_activate(
+        shell: &str,
+        name: &str,
+        config: &Config,
+        before: &[(String, String)],
+        stack: &[Frame],
+    ) -> Result<String, String> {
+        let mut after = MemoryEnvironment::new(before.to_vec());
+        config::apply_config(config, false, false, &mut after)?;
+
+        let mut data = ShellRunCommandFileData::new(shell);
+        let saved = record_changes(before, after.vars(), &mut data);
+
+        let mut stack = stack.to_vec();
+        stack.push(Frame {
+            name: name.to_string(),
+            saved,
+        });
+        data.add(&STACK_KEY.to_string(), &stack_to_string(&stack)?);
+
+        data.build_with_header(&String::new())
+    }
+
+    /// Builds the commands that pop the latest activation and restore the prior values.
+    pub fn build_deactivate(shell: &str, stack: &[Frame]) -> Result<String, String> {
+        let mut stack = stack.to_vec();
+        let frame = match stack.pop() {
+            Some(f) => f,
+            None => return Err("no active environment".to_string()),
+        };
+
+        let mut data = ShellRunCommandFileData::new(shell);
+        record_restore(&frame, &mut data);
+
+        if stack.is_empty() {
+            data.unset(&STACK_KEY.to_string());
+        } else {
+            data.add(&STACK_KEY.to_string(), &stack_to_string(&stack)?);
+        }
+
+        data.build_with_header(&String::new())
+    }
+
+    fn current_stack() -> Result<Vec<Frame>, String> {
+        parse_stack(&std::env::var(STACK_KEY).unwrap_or_default())
+    }
+
+    pub fn activate(settings: &Settings) -> Result<(), String> {
+        let filepath = match (settings.operands.first(), settings.import) {
+            (Some(s), _) => *s,
+            (None, Some(s)) => s,
+            (None, None) => return Err("no config file specified".to_string()),
+        };
+
+        let (shell, _) = resolve_session_shell(settings.shell_rc.shell)?;
+        let config = format::read_config_to_import(
+            Path::new(filepath),
+            settings.input_format,
+            &Translation::from_settings(settings)?,
+        )?;
+        let before = std::env::vars().collect::<Vec<(String, String)>>();
+
+        let script = build_activate(shell, filepath, &config, &before, &current_stack()?)?;
+        println!("{}", script);
+
+        Ok(())
+    }
+
+    pub fn deactivate(settings: &Settings) -> Result<(), String> {
+        let (shell, _) = resolve_session_shell(settings.shell_rc.shell)?;
+
+        let script = build_deactivate(shell, &current_stack()?)?;
+        println!("{}", script);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::activate::activate::{build_activate, build_deactivate, parse_stack};
+    use crate::json::config::Config;
+    use crate::utils::utils::vars;
+
+    fn stack_value(script: &str) -> String {
+        let line = script
+            .lines()
+            .find(|l| l.starts_with("export ENVVAR_ACTIVATE="))
+            .unwrap();
+        let quoted = line.trim_start_matches("export ENVVAR_ACTIVATE=");
+        quoted[1..quoted.len() - 1].replace(r"'\''", "'")
+    }
+
+    #[test]
+    fn test_activate_and_deactivate() {
+        let json = r#"{"version":1,"data":[{"key":"FOO","value":"foo","overwrite":true},{"key":"PATH","value":"/opt/bin","delimiter":":","insert":0}]}"#;
+        let config = serde_json::from_str::<Config>(json).unwrap();
+        let before = vars(&[("PATH", "/usr/bin"), ("HOME", "/home/user")]);
+
+        let activate_result = build_activate("bash", "cfg.json", &config, &before, &Vec::new());
+        assert!(activate_result.is_ok());
+        let script = activate_result.unwrap();
+        assert!(script.lines().any(|l| l == "export FOO=foo"));
+        assert!(script.lines().any(|l| l == "export PATH=/opt/bin:/usr/bin"));
+        assert!(!script.contains("HOME"));
+
+        let stack = parse_stack(&stack_value(&script)).unwrap();
+        assert_eq!(1, stack.len());
+        assert_eq!("cfg.json", stack[0].name);
+
+        let deactivate_result = build_deactivate("bash", &stack);
+        assert!(deactivate_result.is_ok());
+        let script = deactivate_result.unwrap();
+        assert!(script.lines().any(|l| l == "unset FOO"));
+        assert!(script.lines().any(|l| l == "unset ENVVAR_ACTIVATE"));
+        assert!(script.lines().any(|l| l == "export PATH=/usr/bin"));
+    }
+
+    #[test]
+    fn test_deactivate_nested() {
+        let json = r#"{"version":1,"data":[{"key":"FOO","value":"second","overwrite":true}]}"#;
+        let config = serde_json::from_str::<Config>(json).unwrap();
+        let before = vars(&[("FOO", "first")]);
+        let first = r#"[{"name":"a.json","saved":[{"key":"FOO","value":null}]}]"#;
+
+        let script = build_activate(
+            "bash",
+            "b.json",
+            &config,
+            &before,
+            &parse_stack(first).unwrap(),
+        )
+        .unwrap();
+        let stack = parse_stack(&stack_value(&script)).unwrap();
+        assert_eq!(2, stack.len());
+
+        let script = build_deactivate("bash", &stack).unwrap();
+        assert!(script.lines().any(|l| l == "export FOO=first"));
+        assert_eq!(first, stack_value(&script));
+    }
+
+    #[test]
+    fn test_deactivate_empty() {
+        assert!(build_deactivate("bash", &Vec::new()).is_err());
+    }
+}
@@ -1,8 +1,31 @@
 pub mod arguments {
     use crate::settings::settings::Settings;
     #[cfg(target_os = "linux")]
+    use crate::utils::utils::expand_tilde;
+    #[cfg(target_os = "linux")]
     use crate::settings::settings::ShellRc;
 
+    /// Subcommands accepted as the first positional argument.
+    const COMMANDS: [&str; 17] = [
+        "install",
+        "uninstall",
+        "activate",
+        "deactivate",
+        "exec",
+        "diff",
+        "snapshot",
+        "merge",
+        "shell",
+        "hook",
+        "hook-env",
+        "allow",
+        "deny",
+        "from-rc",
+        "capture",
+        "verify-rc",
+        "watch",
+    ];
+
     pub fn args_to_vec() -> Vec<String> {
         std::env::args().collect::<Vec<String>>()
     }
@@ -14,7 +37,7 @@ pub mod arguments {
         let r = args.get(index);
         match r {
             Some(e) => {
-                let s = e.split("=").collect::<Vec<&str>>();
+                let s = e.splitn(2, "=").collect::<Vec<&str>>();
 
                 let key_result = &s.get(0);
                 let value_result = &s.get(1);
@@ -25,6 +48,14 @@ pub mod arguments {
         }
     }
 
+    /// Returns the value of `--name=value`, which must not be blank.
+    fn required_value<'a>(arg: &str, v: Option<&'a str>) -> Result<&'a str, String> {
+        match v {
+            Some(s) if !s.trim().is_empty() => Ok(s),
+            _ => Err(format!("invalid argument: {}", arg)),
+        }
+    }
+
     pub fn parse(args: &Vec<String>) -> Result<Settings, String> {
         let mut settings = Settings {
             no_args: args.len() <= 1,
@@ -33,21 +64,57 @@ pub mod arguments {
             dry_run: false,
             verbose: 0,
             version: false,
+            command: None,
+            operands: Vec::new(),
+            trailing: Vec::new(),
+            clean: false,
+            keep: Vec::new(),
+            filter: Vec::new(),
             export: None,
             import: None,
+            format: None,
+            input_format: None,
+            oci: None,
             set_variable: None,
             system: false,
+            json: false,
+            sync: false,
+            interactive: false,
+            translate: false,
+            platform: None,
+            mount_root: None,
             #[cfg(target_os = "windows")]
             registry: false,
             #[cfg(target_os = "linux")]
             shell_rc: ShellRc {
-                shell: Some("bash"),
+                shell: None,
                 output_rc: None,
+                target: None,
+                login: false,
             },
+            #[cfg(target_os = "linux")]
+            root: None,
+            #[cfg(target_os = "linux")]
+            pid: None,
         };
 
-        for (i, a) in args.iter().enumerate() {
+        for (i, a) in args.iter().enumerate().skip(1) {
+            // the rest is passed through, e.g., the command line for exec
+            if a == "--" {
+                settings.trailing = args[i + 1..].iter().map(|s| s.as_str()).collect();
+                break;
+            }
+
             if !a.starts_with("--") {
+                if settings.command.is_some() {
+                    settings.operands.push(a);
+                    continue;
+                }
+
+                if !COMMANDS.contains(&a.as_str()) {
+                    return Err(format!("unknown command: {}", a));
+                }
+                settings.command = Some(a);
                 continue;
             }
 
@@ -60,37 +127,34 @@ pub mod arguments {
                 "--help" => settings.help = true,
                 "--no-color" => settings.no_color = true,
                 "--dry-run" => settings.dry_run = true,
-                "--export" => {
-                    settings.export = v;
-                    if v.is_none() || v.unwrap().trim().len() <= 0 {
-                        return Err(format!("invalid argument: {}", a));
-                    }
-                }
-                "--import" => {
-                    settings.import = v;
-                    if v.is_none() || v.unwrap().trim().len() <= 0 {
-                        return Err(format!("invalid argument: {}", a));
-                    }
-                }
+                "--clean" => settings.clean = true,
+                "--system" => settings.system = true,
+                "--json" => settings.json = true,
+                "--sync" => settings.sync = true,
+                "--interactive" => settings.interactive = true,
+                "--translate" => settings.translate = true,
+                "--keep" => settings.keep.extend(required_value(a, v)?.split(',')),
+                "--filter" => settings.filter.extend(required_value(a, v)?.split(',')),
+                "--export" => settings.export = Some(required_value(a, v)?),
+                "--import" => settings.import = Some(required_value(a, v)?),
+                "--format" => settings.format = Some(required_value(a, v)?),
+                "--input-format" => settings.input_format = Some(required_value(a, v)?),
+                "--platform" => settings.platform = Some(required_value(a, v)?),
+                "--mount-root" => settings.mount_root = Some(required_value(a, v)?),
+                "--oci" => settings.oci = Some(required_value(a, v)?),
                 #[cfg(target_os = "linux")]
-                "--shell" => {
-                    settings.shell_rc.shell = v;
-                    if v.is_none() || v.unwrap().trim().len() <= 0 {
-                        return Err(format!("invalid argument: {}", a));
-                    }
-
-                    if settings.shell_rc.output_rc.is_none() {
-                        // update default value
-                        settings.shell_rc.output_rc =
-                            Some(format!(".envvar_{}rc", v.unwrap()).to_string());
-                    }
-                }
+                "--pid" => settings.pid = Some(required_value(a, v)?),
+                #[cfg(target_os = "linux")]
+                "--shell" => settings.shell_rc.shell = Some(required_value(a, v)?),
+                #[cfg(target_os = "linux")]
+                "--target" => settings.shell_rc.target = Some(required_value(a, v)?),
+                #[cfg(target_os = "linux")]
+                "--root" => settings.root = Some(required_value(a, v)?),
+                #[cfg(target_os = "linux")]
+                "--login" => settings.shell_rc.login = true,
                 #[cfg(target_os = "linux")]
                 "--rc" => {
-                    if v.is_none() || v.unwrap().trim().len() <= 0 {
-                        return Err(format!("invalid argument: {}", a));
-                    }
-                    settings.shell_rc.output_rc = Some(v.unwrap().to_string());
+                    settings.shell_rc.output_rc = Some(expand_tilde(required_value(a, v)?));
                 }
                 "--verbose" => match v {
                     Some(n_str) => match n_str.parse::<u32>() {
@@ -0,0 +1,166 @@
+pub mod capture {
+    use std::path::Path;
+
+    use crate::format::format;
+    use crate::json::config::{Config, ValueDetail};
+    use crate::probe::probe::probe;
+    use crate::run::run::clean_environment;
+    use crate::settings::settings::Settings;
+    use crate::shellrc::detect::resolve_shell;
+    use crate::shellrc::shellrc::source_command;
+
+    /// Delimiters tried, in order, to recognize a change as an extension of a list.
+    pub const LIST_DELIMITERS: [&str; 2] = [":", ";"];
+
+    /// Returns the elements added before and after `before` if `after` extends it.
+    pub fn find_extension(before: &str, after: &str, delimiter: &str) -> Option<(String, String)> {
+        let old = before.split(delimiter).collect::<Vec<&str>>();
+        let new = after.split(delimiter).collect::<Vec<&str>>();
+        if before.is_empty() || new.len() <= old.len() {
+            return None;
+        }
+
+        let i = (0..=new.len() - old.len()).find(|i| new[*i..*i + old.len()] == old[..])?;
+        Some((
+            new[..i].join(delimiter),
+            new[i + old.len()..].join(delimiter),
+        ))
+    }
+
+    /// Builds the config that turns `before` into `after`. A list extended at either end
+    /// becomes an insertion and an append, other changes overwrite the value.
+    /// Returns the config and the warnings about what could not be expressed.
+    pub fn build_config(
+        before: &[(String, String)],
+        after: &[(String, String)],
+    ) -> (Config, Vec<String>) {
+        let mut data: Vec<ValueDetail> = Vec::new();
+        let mut warnings: Vec<String> = Vec::new();
+
+        for (k, v) in after.iter() {
+            let prior = before.iter().find(|(bk, _)| bk == k).map(|(_, bv)| bv);
+            if prior == Some(v) {
+                continue;
+            }
+
+            let extension = prior.and_then(|p| {
+                LIST_DELIMITERS
+                    .iter()
+                    .find_map(|d| find_extension(p, v, d).map(|e| (d, e)))
+            });
+            match extension {
+                Some((d, (prefix, suffix))) => {
+                    for (value, insert) in [(prefix, 0), (suffix, -1)] {
+                        if value.is_empty() {
+                            continue;
+                        }
+                        data.push(ValueDetail {
+                            key: k.to_string(),
+                            value,
+                            overwrite: false,
+                            delimiter: d.to_string(),
+                            insert,
+                            secret: false,
+                        });
+                    }
+                }
+                None => data.push(ValueDetail {
+                    key: k.to_string(),
+                    value: v.to_string(),
+                    overwrite: true,
+                    delimiter: "".to_string(),
+                    insert: -1,
+                    secret: false,
+                }),
+            }
+        }
+
+        for (k, _) in before.iter() {
+            if !after.iter().any(|(ak, _)| ak == k) {
+                warnings.push(format!(
+                    "{}: removing a variable cannot be expressed in a config, skipped",
+                    k
+                ));
+            }
+        }
+
+        let config = Config {
+            version: 1,
+            data,
+            metadata: None,
+        };
+        (config, warnings)
+    }
+
+    /// Sources the script given as the operand in a clean shell and prints the changes as a config.
+    pub fn capture(settings: &Settings) -> Result<(), String> {
+        let filepath = match settings.operands.first() {
+            Some(s) => *s,
+            None => return Err("no script specified".to_string()),
+        };
+        let filepath = match std::path::absolute(filepath) {
+            Ok(p) if p.is_file() => p.to_string_lossy().to_string(),
+            Ok(_) => return Err(format!("file not found. path: {}", filepath)),
+            Err(e) => return Err(e.to_string()),
+        };
+
+        let (shell, _) = resolve_shell(settings.shell_rc.shell)?;
+        let env = clean_environment(&settings.keep);
+        let dumps = probe(shell, &env, &[source_command(shell, &filepath)?])?;
+
+        let (config, warnings) = build_config(&dumps[0], &dumps[1]);
+        for w in warnings.iter() {
+            eprintln!("warning: {}", w);
+        }
+
+        match settings.export {
+            Some(s) => format::write_config(&config, Path::new(s), settings.format),
+            None => format::print_config(&config, settings.format),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::capture::capture::build_config;
+    use crate::utils::utils::vars;
+
+    #[test]
+    fn test_build_config() {
+        let before = vars(&[("PATH", "/usr/bin:/bin"), ("FOO", "foo"), ("OLD", "old")]);
+        let after = vars(&[
+            ("PATH", "/opt/sdk/bin:/usr/bin:/bin:/opt/sdk/tools"),
+            ("FOO", "bar"),
+            ("SDK_HOME", "/opt/sdk"),
+        ]);
+
+        let (config, warnings) = build_config(&before, &after);
+        assert_eq!(
+            vec!["OLD: removing a variable cannot be expressed in a config, skipped"],
+            warnings
+        );
+
+        let data = config
+            .data
+            .iter()
+            .map(|e| {
+                (
+                    e.key.as_str(),
+                    e.value.as_str(),
+                    e.overwrite,
+                    e.delimiter.as_str(),
+                    e.insert,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                ("PATH", "/opt/sdk/bin", false, ":", 0),
+                ("PATH", "/opt/sdk/tools", false, ":", -1),
+                ("FOO", "bar", true, "", -1),
+                ("SDK_HOME", "/opt/sdk", true, "", -1),
+            ],
+            data
+        );
+    }
+}
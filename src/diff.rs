@@ -0,0 +1,341 @@
+pub mod diff {
+    use std::io::Write;
+    use std::path::Path;
+
+    use serde::Serialize;
+    use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
+
+    use crate::envvar::environment_variable::EnvironmentVariable;
+    use crate::envvar::environment_variable::env::{Environment, PATH_DELIMITER};
+    use crate::envvar::environment_variable::memory::MemoryEnvironment;
+    use crate::format::format;
+    use crate::json::config::{self, Config};
+    use crate::settings::settings::Settings;
+    use crate::snapshot::snapshot;
+    use crate::source::source;
+    use crate::utils;
+
+    /// Variables set by the shell itself, which differ between any two processes.
+    pub const IGNORE_KEYS: [&str; 4] = ["_", "PWD", "OLDPWD", "SHLVL"];
+
+    #[derive(Serialize, Debug, PartialEq)]
+    pub struct Element {
+        /// `added`, `removed` or `moved`
+        pub change: &'static str,
+        pub value: String,
+    }
+
+    #[derive(Serialize, Debug, PartialEq)]
+    pub struct Change {
+        pub key: String,
+        /// `added`, `removed` or `changed`
+        pub change: &'static str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub old: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub new: Option<String>,
+        /// The elements that differ, for a list such as `PATH`
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        pub elements: Vec<Element>,
+    }
+
+    #[derive(Serialize)]
+    struct Report<'a> {
+        a: &'a str,
+        b: &'a str,
+        changes: &'a [Change],
+    }
+
+    /// Returns the values `config` sets on an empty environment. An extension of a list
+    /// such as `PATH=/opt/bin:$PATH` gives only the added part.
+    pub fn config_to_vars(config: &Config) -> Result<Vec<(String, String)>, String> {
+        let mut envvar = MemoryEnvironment::new(Vec::new());
+        config::apply_config(config, false, false, &mut envvar)?;
+        envvar.list()
+    }
+
+    #[cfg(target_os = "linux")]
+    fn read_rc(path: &str, settings: &Settings) -> Result<Vec<(String, String)>, String> {
+        use crate::rcparse::rcparse::{parse_rc, shell_from_filename};
+
+        let shell = match settings.shell_rc.shell {
+            Some(s) => s,
+            None => shell_from_filename(Path::new(path)).unwrap_or("bash"),
+        };
+        let content = match std::fs::read_to_string(path) {
+            Ok(s) => s,
+            Err(e) => return Err(format!("{}: {}", e, path)),
+        };
+
+        let env = std::env::vars().collect::<Vec<(String, String)>>();
+        let (config, warnings) = parse_rc(shell, &content, &env)?;
+        for w in warnings.iter() {
+            eprintln!("warning: {}", w);
+        }
+        config_to_vars(&config)
+    }
+
+    /// Reads one side of `diff`: `env` for the current environment, `pid:<pid>`,
+    /// `oci:<path>`, `rc:<path>`, `snapshot:<name>`, `<format>:<path>` or the path of a config.
+    pub fn read_side(spec: &str, settings: &Settings) -> Result<Vec<(String, String)>, String> {
+        if spec == "env" || spec == "-" {
+            return Environment::new().list();
+        }
+
+        if let Some((prefix, rest)) = spec.split_once(':') {
+            match prefix {
+                #[cfg(target_os = "linux")]
+                "pid" => return source::from_pid(rest)?.list(),
+                "oci" => return source::from_oci(rest)?.list(),
+                "snapshot" => return Ok(snapshot::read(rest)?.vars),
+                #[cfg(target_os = "linux")]
+                "rc" => return read_rc(rest, settings),
+                p if format::resolve(Some(p), Path::new(rest)).is_ok() => {
+                    let config = format::read_config(Path::new(rest), Some(p))?;
+                    return config_to_vars(&config);
+                }
+                _ => {}
+            }
+        }
+
+        let config = format::read_config(Path::new(spec), None)?;
+        config_to_vars(&config)
+    }
+
+    fn is_list(key: &str) -> bool {
+        key.ends_with("PATH") || key.ends_with("_DIRS")
+    }
+
+    /// Compares the elements of two lists. An element found on both sides at a different
+    /// position is reported once as `moved`.
+    fn compare_elements(old: &str, new: &str) -> Vec<Element> {
+        let old = old.split(PATH_DELIMITER).collect::<Vec<&str>>();
+        let new = new.split(PATH_DELIMITER).collect::<Vec<&str>>();
+        let d = utils::utils::diff(&old, &new);
+
+        let mut result: Vec<Element> = Vec::new();
+        for (tag, value) in d.iter() {
+            let on_both = d.iter().any(|(t, v)| v == value && *t != *tag && *t != ' ');
+            let change = match tag {
+                '-' if on_both => continue,
+                '-' => "removed",
+                '+' if on_both => "moved",
+                '+' => "added",
+                _ => continue,
+            };
+            result.push(Element {
+                change,
+                value: value.to_string(),
+            });
+        }
+
+        result
+    }
+
+    /// Lists the variables added, removed and changed from `a` to `b`, sorted by name.
+    pub fn compare(a: &[(String, String)], b: &[(String, String)]) -> Vec<Change> {
+        let find = |list: &[(String, String)], key: &str| {
+            list.iter()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| v.to_string())
+        };
+
+        let mut keys = a.iter().chain(b.iter()).map(|(k, _)| k).collect::<Vec<_>>();
+        keys.sort();
+        keys.dedup();
+
+        let mut result: Vec<Change> = Vec::new();
+        for key in keys {
+            let old = find(a, key);
+            let new = find(b, key);
+            let (change, elements) = match (&old, &new) {
+                (Some(o), Some(n)) if o == n => continue,
+                (Some(o), Some(n)) if is_list(key) => ("changed", compare_elements(o, n)),
+                (Some(_), Some(_)) => ("changed", Vec::new()),
+                (Some(_), None) => ("removed", Vec::new()),
+                _ => ("added", Vec::new()),
+            };
+            result.push(Change {
+                key: key.to_string(),
+                change,
+                old,
+                new,
+                elements,
+            });
+        }
+
+        result
+    }
+
+    fn print_changes(changes: &[Change], no_color: bool) -> std::io::Result<()> {
+        let choice = if no_color {
+            ColorChoice::Never
+        } else {
+            ColorChoice::Always
+        };
+        let mut stdout = StandardStream::stdout(choice);
+        let red = ColorSpec::new().set_fg(Some(Color::Red)).clone();
+        let green = ColorSpec::new().set_fg(Some(Color::Green)).clone();
+        let yellow = ColorSpec::new().set_fg(Some(Color::Yellow)).clone();
+
+        for c in changes.iter() {
+            let old = c.old.as_deref().unwrap_or_default();
+            let new = c.new.as_deref().unwrap_or_default();
+            match c.change {
+                "removed" => {
+                    stdout.set_color(&red)?;
+                    writeln!(&mut stdout, "- {}={}", c.key, old)?;
+                }
+                "added" => {
+                    stdout.set_color(&green)?;
+                    writeln!(&mut stdout, "+ {}={}", c.key, new)?;
+                }
+                _ => {
+                    stdout.set_color(&yellow)?;
+                    writeln!(&mut stdout, "~ {}", c.key)?;
+                    if c.elements.is_empty() {
+                        stdout.set_color(&red)?;
+                        writeln!(&mut stdout, "    - {}", old)?;
+                        stdout.set_color(&green)?;
+                        writeln!(&mut stdout, "    + {}", new)?;
+                    }
+                    for e in c.elements.iter() {
+                        match e.change {
+                            "removed" => {
+                                stdout.set_color(&red)?;
+                                writeln!(&mut stdout, "    - {}", e.value)?;
+                            }
+                            "added" => {
+                                stdout.set_color(&green)?;
+                                writeln!(&mut stdout, "    + {}", e.value)?;
+                            }
+                            _ => {
+                                stdout.set_color(&yellow)?;
+                                writeln!(&mut stdout, "    ~ {} (moved)", e.value)?;
+                            }
+                        }
+                    }
+                }
+            }
+            stdout.reset()?;
+        }
+
+        if !changes.is_empty() {
+            let count = |change: &str| changes.iter().filter(|c| c.change == change).count();
+            writeln!(
+                &mut stdout,
+                "{} added, {} removed, {} changed",
+                count("added"),
+                count("removed"),
+                count("changed")
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Prints the differences between the two sides given as operands, only for the
+    /// variables matching --filter unless it is empty.
+    pub fn diff(settings: &Settings) -> Result<(), String> {
+        let (a, b) = match settings.operands[..] {
+            [a, b] => (a, b),
+            _ => return Err("diff needs two sources (e.g., envvar diff env pid:1)".to_string()),
+        };
+
+        let read = |spec: &str| -> Result<Vec<(String, String)>, String> {
+            Ok(read_side(spec, settings)?
+                .into_iter()
+                .filter(|(k, _)| !IGNORE_KEYS.contains(&k.as_str()))
+                .filter(|(k, _)| {
+                    settings.filter.is_empty()
+                        || settings.filter.iter().any(|p| format::matches(p, k))
+                })
+                .collect())
+        };
+        let changes = compare(&read(a)?, &read(b)?);
+
+        if settings.json {
+            let report = Report {
+                a,
+                b,
+                changes: &changes,
+            };
+            return match serde_json::to_string_pretty(&report) {
+                Ok(s) => {
+                    println!("{}", s);
+                    Ok(())
+                }
+                Err(e) => Err(e.to_string()),
+            };
+        }
+
+        match print_changes(&changes, settings.no_color) {
+            Ok(_) => Ok(()),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diff::diff::{Element, compare, config_to_vars};
+    use crate::utils::utils::vars;
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_compare() {
+        let a = vars(&[
+            ("PATH", "/usr/local/bin:/usr/bin:/bin"),
+            ("SAME", "x"),
+            ("LANG", "C"),
+            ("OLD", "1"),
+        ]);
+        let b = vars(&[
+            ("PATH", "/usr/bin:/opt/bin:/usr/local/bin:/bin"),
+            ("SAME", "x"),
+            ("LANG", "en_US.UTF-8"),
+            ("NEW", "2"),
+        ]);
+        let changes = compare(&a, &b);
+        assert_eq!(
+            vec![
+                ("LANG", "changed"),
+                ("NEW", "added"),
+                ("OLD", "removed"),
+                ("PATH", "changed"),
+            ],
+            changes
+                .iter()
+                .map(|c| (c.key.as_str(), c.change))
+                .collect::<Vec<_>>()
+        );
+        assert!(changes[0].elements.is_empty());
+        assert_eq!(
+            vec![
+                Element {
+                    change: "added",
+                    value: "/opt/bin".to_string()
+                },
+                Element {
+                    change: "moved",
+                    value: "/usr/local/bin".to_string()
+                },
+            ],
+            changes[3].elements
+        );
+    }
+
+    #[test]
+    fn test_config_to_vars() {
+        let json = r#"{"version":1,"data":[
+            {"key":"PATH","value":"/opt/bin","delimiter":":","insert":0},
+            {"key":"APP","value":"first"},
+            {"key":"APP","value":"second","overwrite":true}
+        ]}"#;
+        let config = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            vars(&[("PATH", "/opt/bin"), ("APP", "second")]),
+            config_to_vars(&config).unwrap()
+        );
+    }
+}
@@ -0,0 +1,369 @@
+pub mod docker {
+    use serde_yaml::{Mapping, Value};
+
+    use crate::dotenv::dotenv;
+    use crate::json::config::{Config, ValueDetail};
+
+    fn find<'a>(env: &'a [(String, String)], key: &str) -> Option<&'a String> {
+        env.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+
+    fn overwrite(key: &str, value: &str) -> ValueDetail {
+        ValueDetail {
+            key: key.to_string(),
+            value: value.to_string(),
+            overwrite: true,
+            delimiter: "".to_string(),
+            insert: -1,
+            secret: false,
+        }
+    }
+
+    /// Converts a Docker env-file into a config. Values are taken literally, and a name
+    /// without `=` takes its value from `env`.
+    /// Returns the config and the warnings about what could not be converted.
+    pub fn parse_env_file(
+        content: &str,
+        env: &[(String, String)],
+    ) -> Result<(Config, Vec<String>), String> {
+        let mut data: Vec<ValueDetail> = Vec::new();
+        let mut warnings: Vec<String> = Vec::new();
+
+        for (i, line) in content.lines().enumerate() {
+            let line = line.trim_start();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (key, value) = match line.split_once('=') {
+                Some((k, v)) => (k, Some(v)),
+                None => (line.trim_end(), None),
+            };
+            if key.is_empty() || key.contains(char::is_whitespace) {
+                return Err(format!("line {}: invalid variable name: {:?}", i + 1, key));
+            }
+
+            let value = match value.or(find(env, key).map(|v| v.as_str())) {
+                Some(v) => v,
+                None => {
+                    warnings.push(format!("{}: not set in the environment, skipped", key));
+                    continue;
+                }
+            };
+            data.retain(|e| e.key != key);
+            data.push(overwrite(key, value));
+        }
+
+        Ok((
+            Config {
+                version: 1,
+                data,
+                metadata: None,
+            },
+            warnings,
+        ))
+    }
+
+    /// Writes a config as a Docker env-file, which has neither quoting nor references,
+    /// so multiline values and extensions of a list are skipped.
+    pub fn config_to_env_file(config: &Config) -> (String, Vec<String>) {
+        let mut result = String::new();
+        let mut warnings: Vec<String> = Vec::new();
+
+        for e in config.data.iter() {
+            if e.key.is_empty() || e.key.contains(|c: char| c.is_whitespace() || c == '=') {
+                warnings.push(format!("{}: invalid name for an env-file, skipped", e.key));
+            } else if e.value.contains(['\n', '\r']) {
+                warnings.push(format!(
+                    "{}: multiline values cannot be expressed in an env-file, skipped",
+                    e.key
+                ));
+            } else if !e.overwrite && !e.delimiter.is_empty() {
+                warnings.push(format!(
+                    "{}: extending a list cannot be expressed in an env-file, skipped",
+                    e.key
+                ));
+            } else {
+                result.push_str(&format!("{}={}\n", e.key, e.value));
+            }
+        }
+
+        (result, warnings)
+    }
+
+    fn scalar_to_string(value: &Value) -> Option<String> {
+        match value {
+            Value::String(s) => Some(s.to_string()),
+            Value::Number(n) => Some(n.to_string()),
+            Value::Bool(b) => Some(b.to_string()),
+            _ => None,
+        }
+    }
+
+    /// Reads the `environment:` section, as a mapping or a list of `KEY=VALUE`.
+    /// A name without a value is `None`.
+    fn read_environment(value: &Value) -> Result<Vec<(String, Option<String>)>, String> {
+        let mut result: Vec<(String, Option<String>)> = Vec::new();
+
+        match value {
+            Value::Mapping(m) => {
+                for (k, v) in m.iter() {
+                    let key = match scalar_to_string(k) {
+                        Some(s) => s,
+                        None => return Err("environment: invalid variable name".to_string()),
+                    };
+                    let value = match v {
+                        Value::Null => None,
+                        v => match scalar_to_string(v) {
+                            Some(s) => Some(s),
+                            None => return Err(format!("environment: {}: invalid value", key)),
+                        },
+                    };
+                    result.push((key, value));
+                }
+            }
+            Value::Sequence(list) => {
+                for v in list.iter() {
+                    let entry = match v {
+                        Value::String(s) => s,
+                        _ => return Err("environment: expected KEY=VALUE".to_string()),
+                    };
+                    match entry.split_once('=') {
+                        Some((k, v)) => result.push((k.to_string(), Some(v.to_string()))),
+                        None => result.push((entry.to_string(), None)),
+                    }
+                }
+            }
+            Value::Null => {}
+            _ => return Err("environment: expected a mapping or a list".to_string()),
+        }
+
+        Ok(result)
+    }
+
+    /// Converts the `environment:` section of a Compose file into a config. The sections of
+    /// all services are merged, and a file holding only `environment:` is accepted as well.
+    /// `${NAME}` is expanded with `env` and `$$` stands for `$`.
+    /// Returns the config and the warnings about what could not be converted.
+    pub fn parse_compose(
+        content: &str,
+        env: &[(String, String)],
+    ) -> Result<(Config, Vec<String>), String> {
+        let document = match serde_yaml::from_str::<Value>(content) {
+            Ok(v) => v,
+            Err(e) => return Err(e.to_string()),
+        };
+
+        let mut sections: Vec<(String, &Value)> = Vec::new();
+        if let Some(v) = document.get("environment") {
+            sections.push(("".to_string(), v));
+        }
+        if let Some(Value::Mapping(services)) = document.get("services") {
+            for (name, service) in services.iter() {
+                if let Some(v) = service.get("environment") {
+                    let name = scalar_to_string(name).unwrap_or_default();
+                    sections.push((name, v));
+                }
+            }
+        }
+        if sections.is_empty() {
+            return Err("no environment section found".to_string());
+        }
+
+        let mut entries: Vec<(String, String)> = Vec::new();
+        let mut origins: Vec<(String, String)> = Vec::new();
+        let mut warnings: Vec<String> = Vec::new();
+        for (service, section) in sections.iter() {
+            for (k, v) in read_environment(section)? {
+                // a name without a value is passed through from the environment as is
+                let value = match v.or(find(env, &k).map(|s| s.replace('$', "$$"))) {
+                    Some(v) => v,
+                    None => {
+                        warnings.push(format!("{}: not set in the environment, skipped", k));
+                        continue;
+                    }
+                };
+
+                if let Some(i) = entries.iter().position(|(ek, _)| *ek == k) {
+                    if entries[i].1 != value {
+                        warnings.push(format!(
+                            "{}: differs between services {} and {}, using the one from {}",
+                            k, origins[i].1, service, service
+                        ));
+                    }
+                    entries.remove(i);
+                    origins.remove(i);
+                }
+                entries.push((k.to_string(), value));
+                origins.push((k, service.to_string()));
+            }
+        }
+
+        let (config, mut expand_warnings) = dotenv::parse_entries(&entries, env);
+        warnings.append(&mut expand_warnings);
+
+        Ok((config, warnings))
+    }
+
+    /// Writes a config as the `environment:` section of a Compose file. Extensions of a list
+    /// refer to the current value with `${NAME}`, which Compose expands from the environment.
+    pub fn config_to_compose(config: &Config) -> Result<(String, Vec<String>), String> {
+        let mut values: Vec<(String, String)> = Vec::new();
+        let mut warnings: Vec<String> = Vec::new();
+
+        for e in config.data.iter() {
+            let value = e.value.replace('$', "$$");
+            let current = values.iter().position(|(k, _)| *k == e.key);
+            let base = match current {
+                Some(i) => values.remove(i).1,
+                None => format!("${{{}}}", e.key),
+            };
+
+            let value = match (e.overwrite, e.delimiter.is_empty(), e.insert) {
+                (true, _, _) | (false, true, _) => value,
+                (false, false, 0) => format!("{}{}{}", value, e.delimiter, base),
+                (false, false, i) if i < 0 => format!("{}{}{}", base, e.delimiter, value),
+                (false, false, i) => {
+                    warnings.push(format!(
+                        "{}: inserting at index {} cannot be expressed in a Compose file, skipped",
+                        e.key, i
+                    ));
+                    if current.is_some() {
+                        values.push((e.key.to_string(), base));
+                    }
+                    continue;
+                }
+            };
+            values.push((e.key.to_string(), value));
+        }
+
+        let mut environment = Mapping::new();
+        for (k, v) in values {
+            environment.insert(Value::String(k), Value::String(v));
+        }
+        let mut document = Mapping::new();
+        document.insert(
+            Value::String("environment".to_string()),
+            Value::Mapping(environment),
+        );
+
+        match serde_yaml::to_string(&document) {
+            Ok(s) => Ok((s, warnings)),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::docker::docker::{
+        config_to_compose, config_to_env_file, parse_compose, parse_env_file,
+    };
+    use crate::utils::utils::vars;
+
+    #[test]
+    fn test_env_file() {
+        let env = vars(&[("INHERITED", "from env")]);
+        let content = "# comment\n  FOO=\"not quoted\" $HOME\nINHERITED\nMISSING\nEMPTY=\n";
+        let (config, warnings) = parse_env_file(content, &env).unwrap();
+        assert_eq!(
+            vec!["MISSING: not set in the environment, skipped"],
+            warnings
+        );
+
+        let data = config
+            .data
+            .iter()
+            .map(|e| (e.key.as_str(), e.value.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                ("FOO", "\"not quoted\" $HOME"),
+                ("INHERITED", "from env"),
+                ("EMPTY", ""),
+            ],
+            data
+        );
+
+        let (content, warnings) = config_to_env_file(&config);
+        assert!(warnings.is_empty());
+        assert_eq!(
+            "FOO=\"not quoted\" $HOME\nINHERITED=from env\nEMPTY=\n",
+            content
+        );
+
+        assert!(parse_env_file("BAD KEY=1\n", &env).is_err());
+    }
+
+    #[test]
+    fn test_env_file_warnings() {
+        let json = r#"{"version":1,"data":[
+            {"key":"LINES","value":"first\nsecond","overwrite":true},
+            {"key":"PATH","value":"/opt/bin","delimiter":":","insert":0},
+            {"key":"KEPT","value":"kept","overwrite":true}
+        ]}"#;
+        let config = serde_json::from_str(json).unwrap();
+
+        let (content, warnings) = config_to_env_file(&config);
+        assert_eq!("KEPT=kept\n", content);
+        assert_eq!(2, warnings.len());
+    }
+
+    #[test]
+    fn test_compose() {
+        let env = vars(&[("PATH", "/usr/bin"), ("USER", "me")]);
+        let content = r#"
+services:
+  web:
+    environment:
+      PORT: 8080
+      DEBUG: true
+      PRICE: "$$5"
+      USER:
+      PATH: /opt/bin:${PATH}
+  worker:
+    environment:
+      - QUEUE=jobs
+      - PORT=9090
+"#;
+        let (config, warnings) = parse_compose(content, &env).unwrap();
+        assert_eq!(
+            vec!["PORT: differs between services web and worker, using the one from worker"],
+            warnings
+        );
+
+        let data = config
+            .data
+            .iter()
+            .map(|e| (e.key.as_str(), e.value.as_str(), e.insert))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                ("DEBUG", "true", -1),
+                ("PRICE", "$5", -1),
+                ("USER", "me", -1),
+                ("PATH", "/opt/bin", 0),
+                ("QUEUE", "jobs", -1),
+                ("PORT", "9090", -1),
+            ],
+            data
+        );
+
+        let (content, warnings) = config_to_compose(&config).unwrap();
+        assert!(warnings.is_empty());
+        assert!(content.starts_with("environment:\n"));
+        let (read_back, _) = parse_compose(&content, &env).unwrap();
+        assert_eq!(
+            config
+                .data
+                .iter()
+                .map(|e| (&e.key, &e.value, e.insert))
+                .collect::<Vec<_>>(),
+            read_back
+                .data
+                .iter()
+                .map(|e| (&e.key, &e.value, e.insert))
+                .collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,557 @@
+pub mod dotenv {
+    use std::collections::HashMap;
+
+    use crate::json::config::{Config, ValueDetail};
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum Part {
+        Text(String),
+        /// `${NAME}`, or `${NAME:-default}` whose default is used when unset or empty.
+        Var {
+            name: String,
+            default: Option<String>,
+            if_empty: bool,
+        },
+    }
+
+    fn push_char(parts: &mut Vec<Part>, c: char) {
+        match parts.last_mut() {
+            Some(Part::Text(s)) => s.push(c),
+            _ => parts.push(Part::Text(c.to_string())),
+        }
+    }
+
+    fn is_name_char(c: char) -> bool {
+        c.is_ascii_alphanumeric() || c == '_'
+    }
+
+    fn is_key_char(c: char) -> bool {
+        is_name_char(c) || c == '.' || c == '-'
+    }
+
+    /// Reads `$NAME`, `${NAME}` or `${NAME:-default}` at `i`, returning `None` for a lone `$`.
+    fn read_variable(chars: &[char], i: &mut usize) -> Option<Part> {
+        let mut j = *i + 1;
+        if chars.get(j) == Some(&'{') {
+            let end = chars[j..].iter().position(|c| *c == '}')? + j;
+            let inner = chars[j + 1..end].iter().collect::<String>();
+            let name = inner
+                .chars()
+                .take_while(|c| is_name_char(*c))
+                .collect::<String>();
+            if name.is_empty() {
+                return None;
+            }
+
+            let rest = &inner[name.len()..];
+            let (default, if_empty) = match rest {
+                "" => (None, false),
+                _ if rest.starts_with(":-") => (Some(rest[2..].to_string()), true),
+                _ if rest.starts_with('-') => (Some(rest[1..].to_string()), false),
+                _ => return None,
+            };
+
+            *i = end + 1;
+            return Some(Part::Var {
+                name,
+                default,
+                if_empty,
+            });
+        }
+
+        while j < chars.len() && is_name_char(chars[j]) {
+            j += 1;
+        }
+        if j == *i + 1 {
+            return None;
+        }
+
+        let name = chars[*i + 1..j].iter().collect::<String>();
+        *i = j;
+        Some(Part::Var {
+            name,
+            default: None,
+            if_empty: false,
+        })
+    }
+
+    /// Reads the value at `i` up to the end of its line.
+    fn read_value(chars: &[char], i: &mut usize) -> Result<Vec<Part>, String> {
+        let mut parts: Vec<Part> = Vec::new();
+
+        match chars.get(*i) {
+            // single quotes and backticks are taken literally
+            Some(q @ ('\'' | '`')) => {
+                let q = *q;
+                *i += 1;
+                while *i < chars.len() && chars[*i] != q {
+                    push_char(&mut parts, chars[*i]);
+                    *i += 1;
+                }
+                if *i >= chars.len() {
+                    return Err(format!("unterminated quote: {}", q));
+                }
+                *i += 1;
+            }
+            Some('"') => {
+                *i += 1;
+                while *i < chars.len() && chars[*i] != '"' {
+                    match chars[*i] {
+                        '\\' if *i + 1 < chars.len() => {
+                            *i += 1;
+                            match chars[*i] {
+                                'n' => push_char(&mut parts, '\n'),
+                                'r' => push_char(&mut parts, '\r'),
+                                't' => push_char(&mut parts, '\t'),
+                                c @ ('\\' | '"' | '$' | '\'') => push_char(&mut parts, c),
+                                c => {
+                                    push_char(&mut parts, '\\');
+                                    push_char(&mut parts, c);
+                                }
+                            }
+                            *i += 1;
+                        }
+                        '$' => match read_variable(chars, i) {
+                            Some(v) => parts.push(v),
+                            None => {
+                                push_char(&mut parts, '$');
+                                *i += 1;
+                            }
+                        },
+                        c => {
+                            push_char(&mut parts, c);
+                            *i += 1;
+                        }
+                    }
+                }
+                if *i >= chars.len() {
+                    return Err("unterminated quote: \"".to_string());
+                }
+                *i += 1;
+            }
+            _ => {
+                let start = *i;
+                while *i < chars.len() && chars[*i] != '\n' {
+                    // a comment starts with whitespace and #
+                    if chars[*i] == '#' && *i > start && chars[*i - 1].is_whitespace() {
+                        break;
+                    }
+                    *i += 1;
+                }
+                let text = chars[start..*i].iter().collect::<String>();
+                let text = text.trim_end().chars().collect::<Vec<char>>();
+
+                let mut j = 0;
+                while j < text.len() {
+                    if text[j] == '$'
+                        && let Some(v) = read_variable(&text, &mut j)
+                    {
+                        parts.push(v);
+                        continue;
+                    }
+                    push_char(&mut parts, text[j]);
+                    j += 1;
+                }
+            }
+        }
+
+        // the rest of the line may only hold a comment
+        while *i < chars.len() && chars[*i] != '\n' {
+            *i += 1;
+        }
+
+        Ok(parts)
+    }
+
+    /// Builds a config from the parsed entries, tracking the values for interpolation.
+    struct Builder {
+        values: HashMap<String, String>,
+        data: Vec<ValueDetail>,
+        warnings: Vec<String>,
+    }
+
+    impl Builder {
+        fn expand(&mut self, key: &str, parts: &[Part]) -> String {
+            let mut result = String::new();
+            for p in parts {
+                match p {
+                    Part::Text(t) => result.push_str(t),
+                    Part::Var {
+                        name,
+                        default,
+                        if_empty,
+                    } => match (self.values.get(name), default) {
+                        (Some(v), Some(d)) if v.is_empty() && *if_empty => result.push_str(d),
+                        (Some(v), _) => result.push_str(v),
+                        (None, Some(d)) => result.push_str(d),
+                        (None, None) => self
+                            .warnings
+                            .push(format!("{}: ${} is not set, expanded to empty", key, name)),
+                    },
+                }
+            }
+
+            result
+        }
+
+        fn set(&mut self, key: &str, parts: &[Part]) {
+            let own = parts
+                .iter()
+                .enumerate()
+                .filter(|(_, p)| matches!(p, Part::Var { name, default: None, .. } if name == key))
+                .map(|(i, _)| i)
+                .collect::<Vec<usize>>();
+
+            // `${KEY}` next to a delimiter extends the list instead of replacing it
+            if let [i] = own.as_slice() {
+                let prefix = self.expand(key, &parts[..*i]);
+                let suffix = self.expand(key, &parts[i + 1..]);
+                let d = prefix.chars().last().or(suffix.chars().next());
+
+                if let Some(d) = d.filter(|d| *d == ':' || *d == ';')
+                    && (prefix.is_empty() || prefix.ends_with(d))
+                    && (suffix.is_empty() || suffix.starts_with(d))
+                    && !self.data.iter().any(|e| e.key == key)
+                {
+                    let current = self.values.get(key).cloned().unwrap_or_default();
+                    let prefix = prefix.trim_end_matches(d);
+                    let suffix = suffix.trim_start_matches(d);
+                    for (value, insert) in [(prefix, 0), (suffix, -1)] {
+                        if value.is_empty() {
+                            continue;
+                        }
+                        self.data.push(ValueDetail {
+                            key: key.to_string(),
+                            value: value.to_string(),
+                            overwrite: false,
+                            delimiter: d.to_string(),
+                            insert,
+                            secret: false,
+                        });
+                    }
+
+                    let value = [prefix, &current, suffix]
+                        .iter()
+                        .filter(|s| !s.is_empty())
+                        .copied()
+                        .collect::<Vec<&str>>()
+                        .join(&d.to_string());
+                    self.values.insert(key.to_string(), value);
+                    return;
+                }
+            }
+
+            let value = self.expand(key, parts);
+            self.data.retain(|e| e.key != key);
+            self.data.push(ValueDetail {
+                key: key.to_string(),
+                value: value.to_string(),
+                overwrite: true,
+                delimiter: "".to_string(),
+                insert: -1,
+                secret: false,
+            });
+            self.values.insert(key.to_string(), value);
+        }
+    }
+
+    /// Converts the content of a `.env` file into a config. References to variables are
+    /// expanded with the earlier entries and `env`.
+    /// Returns the config and the warnings about what could not be converted.
+    pub fn parse(content: &str, env: &[(String, String)]) -> Result<(Config, Vec<String>), String> {
+        let chars = content.chars().collect::<Vec<char>>();
+        let mut builder = Builder {
+            values: env.iter().cloned().collect(),
+            data: Vec::new(),
+            warnings: Vec::new(),
+        };
+
+        let mut i = 0;
+        while i < chars.len() {
+            if chars[i].is_whitespace() {
+                i += 1;
+                continue;
+            }
+            let line = chars[..i].iter().filter(|c| **c == '\n').count() + 1;
+            if chars[i] == '#' {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+                continue;
+            }
+
+            let mut start = i;
+            let mut end = i;
+            while end < chars.len() && is_key_char(chars[end]) {
+                end += 1;
+            }
+            let word = chars[start..end].iter().collect::<String>();
+            if word == "export" && chars.get(end).is_some_and(|c| *c == ' ' || *c == '\t') {
+                start = end;
+                while start < chars.len() && (chars[start] == ' ' || chars[start] == '\t') {
+                    start += 1;
+                }
+                end = start;
+                while end < chars.len() && is_key_char(chars[end]) {
+                    end += 1;
+                }
+            }
+
+            let key = chars[start..end].iter().collect::<String>();
+            i = end;
+            while i < chars.len() && (chars[i] == ' ' || chars[i] == '\t') {
+                i += 1;
+            }
+            if key.is_empty() || chars.get(i) != Some(&'=') {
+                return Err(format!("line {}: expected KEY=VALUE", line));
+            }
+            i += 1;
+            while i < chars.len() && (chars[i] == ' ' || chars[i] == '\t') {
+                i += 1;
+            }
+
+            let parts = match read_value(&chars, &mut i) {
+                Ok(p) => p,
+                Err(e) => return Err(format!("line {}: {}", line, e)),
+            };
+            builder.set(&key, &parts);
+        }
+
+        let config = Config {
+            version: 1,
+            data: builder.data,
+            metadata: None,
+        };
+
+        Ok((config, builder.warnings))
+    }
+
+    /// Splits a value into text and references, where `$$` stands for `$` as in Compose files.
+    fn split_references(value: &str) -> Vec<Part> {
+        let chars = value.chars().collect::<Vec<char>>();
+        let mut parts: Vec<Part> = Vec::new();
+
+        let mut i = 0;
+        while i < chars.len() {
+            if chars[i] == '$' {
+                if chars.get(i + 1) == Some(&'$') {
+                    push_char(&mut parts, '$');
+                    i += 2;
+                    continue;
+                }
+                if let Some(v) = read_variable(&chars, &mut i) {
+                    parts.push(v);
+                    continue;
+                }
+            }
+            push_char(&mut parts, chars[i]);
+            i += 1;
+        }
+
+        parts
+    }
+
+    /// Converts entries whose values refer to variables with `${NAME}` into a config,
+    /// in the same way as the lines of a `.env` file.
+    pub fn parse_entries(
+        entries: &[(String, String)],
+        env: &[(String, String)],
+    ) -> (Config, Vec<String>) {
+        let mut builder = Builder {
+            values: env.iter().cloned().collect(),
+            data: Vec::new(),
+            warnings: Vec::new(),
+        };
+        for (k, v) in entries.iter() {
+            builder.set(k, &split_references(v));
+        }
+
+        let config = Config {
+            version: 1,
+            data: builder.data,
+            metadata: None,
+        };
+
+        (config, builder.warnings)
+    }
+
+    fn is_valid_key(key: &str) -> bool {
+        !key.is_empty()
+            && !key.starts_with(|c: char| c.is_ascii_digit())
+            && key.chars().all(is_name_char)
+    }
+
+    fn escape_double(value: &str) -> String {
+        let mut result = String::new();
+        for c in value.chars() {
+            match c {
+                '\\' => result.push_str("\\\\"),
+                '"' => result.push_str("\\\""),
+                '$' => result.push_str("\\$"),
+                '\n' => result.push_str("\\n"),
+                '\r' => result.push_str("\\r"),
+                '\t' => result.push_str("\\t"),
+                _ => result.push(c),
+            }
+        }
+
+        result
+    }
+
+    /// Quotes `value` so that it is read back as is.
+    pub fn quote(value: &str) -> String {
+        let is_safe = |c: char| c.is_ascii_alphanumeric() || "_./:@%+,-".contains(c);
+        if !value.is_empty() && value.chars().all(is_safe) {
+            return value.to_string();
+        }
+
+        if !value.contains(['\'', '\n', '\r']) {
+            return format!("'{}'", value);
+        }
+
+        format!("\"{}\"", escape_double(value))
+    }
+
+    /// Writes the variables as a `.env` file, skipping the names it cannot hold.
+    pub fn vars_to_string(list: &[(String, String)]) -> (String, Vec<String>) {
+        let mut lines: Vec<String> = Vec::new();
+        let mut warnings: Vec<String> = Vec::new();
+
+        for (k, v) in list.iter() {
+            if !is_valid_key(k) {
+                warnings.push(format!("{}: invalid name for .env, skipped", k));
+                continue;
+            }
+            lines.push(format!("{}={}", k, quote(v)));
+        }
+
+        (lines.iter().map(|l| format!("{}\n", l)).collect(), warnings)
+    }
+
+    /// Writes a config as a `.env` file. Insertions at the head and appends of a list
+    /// refer to the current value with `${NAME}`.
+    pub fn config_to_string(config: &Config) -> (String, Vec<String>) {
+        let mut lines: Vec<String> = Vec::new();
+        let mut warnings: Vec<String> = Vec::new();
+
+        for e in config.data.iter() {
+            if !is_valid_key(&e.key) {
+                warnings.push(format!("{}: invalid name for .env, skipped", e.key));
+                continue;
+            }
+
+            let value = match (e.overwrite, e.delimiter.is_empty(), e.insert) {
+                (true, _, _) | (false, true, _) => quote(&e.value),
+                (false, false, 0) => format!(
+                    "\"{}{}${{{}}}\"",
+                    escape_double(&e.value),
+                    escape_double(&e.delimiter),
+                    e.key
+                ),
+                (false, false, i) if i < 0 => format!(
+                    "\"${{{}}}{}{}\"",
+                    e.key,
+                    escape_double(&e.delimiter),
+                    escape_double(&e.value)
+                ),
+                (false, false, i) => {
+                    warnings.push(format!(
+                        "{}: inserting at index {} cannot be expressed in .env, skipped",
+                        e.key, i
+                    ));
+                    continue;
+                }
+            };
+            lines.push(format!("{}={}", e.key, value));
+        }
+
+        (lines.iter().map(|l| format!("{}\n", l)).collect(), warnings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dotenv::dotenv::{config_to_string, parse, vars_to_string};
+    use crate::utils::utils::vars;
+
+    fn entries(content: &str) -> Vec<(String, String, bool, String, i32)> {
+        let env = vars(&[("HOME", "/home/user"), ("PATH", "/usr/bin")]);
+        let (config, _) = parse(content, &env).unwrap();
+        config
+            .data
+            .into_iter()
+            .map(|e| (e.key, e.value, e.overwrite, e.delimiter, e.insert))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        let content = r#"
+# comment
+export PLAIN = value with spaces # trailing comment
+SINGLE='it is $HOME # not a comment'
+DOUBLE="line1\nline2 \"quoted\" ${HOME}"
+MULTI="first
+second"
+BACKTICK=`raw ${HOME}`
+DEFAULT=${UNSET:-fallback}
+REF=$PLAIN/ref
+PATH=/opt/bin:${PATH}
+EMPTY=
+"#;
+        let data = entries(content);
+        let value = |k: &str| data.iter().find(|e| e.0 == k).unwrap().1.to_string();
+
+        assert_eq!("value with spaces", value("PLAIN"));
+        assert_eq!("it is $HOME # not a comment", value("SINGLE"));
+        assert_eq!("line1\nline2 \"quoted\" /home/user", value("DOUBLE"));
+        assert_eq!("first\nsecond", value("MULTI"));
+        assert_eq!("raw ${HOME}", value("BACKTICK"));
+        assert_eq!("fallback", value("DEFAULT"));
+        assert_eq!("value with spaces/ref", value("REF"));
+        assert_eq!("", value("EMPTY"));
+        assert!(data.contains(&(
+            "PATH".to_string(),
+            "/opt/bin".to_string(),
+            false,
+            ":".to_string(),
+            0
+        )));
+    }
+
+    #[test]
+    fn test_parse_error() {
+        assert!(parse("NOT A LINE\n", &Vec::new()).is_err());
+        assert!(parse("KEY=\"unterminated\n", &Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_parse_export_only() {
+        assert_eq!(
+            Err("line 1: expected KEY=VALUE".to_string()),
+            parse("export ", &Vec::new()).map(|_| ())
+        );
+    }
+
+    #[test]
+    fn test_write_and_read_back() {
+        let list = vars(&[
+            ("SIMPLE", "/usr/bin"),
+            ("SPACES", "a b $c"),
+            ("QUOTES", "it's \"x\"\nnext $HOME \\"),
+            ("EMPTY", ""),
+            ("INVALID-NAME", "x"),
+        ]);
+        let (content, warnings) = vars_to_string(&list);
+        assert_eq!(1, warnings.len());
+
+        let (config, _) = parse(&content, &Vec::new()).unwrap();
+        assert_eq!(4, config.data.len());
+        for (e, (k, v)) in config.data.iter().zip(list.iter()) {
+            assert_eq!((k, v), (&e.key, &e.value));
+        }
+
+        let (content, _) = config_to_string(&config);
+        assert!(content.starts_with("SIMPLE=/usr/bin\nSPACES='a b $c'\n"));
+    }
+}
@@ -12,10 +12,10 @@ pub mod environment_variable {
         fn get_list(&self, name: &String, delimiter: &String) -> Result<Vec<String>, String> {
             match self.get(name) {
                 Ok(e) => {
-                    let chars = &e.chars().collect::<Vec<char>>();
-                    let removed_terminal = &chars[0..e.len() - 1].iter().collect::<String>();
+                    // values read from the registry keep their terminal character
+                    let removed_terminal = e.strip_suffix('\0').unwrap_or(&e);
                     Ok(removed_terminal
-                        .split(delimiter)
+                        .split(delimiter.as_str())
                         .map(|s| s.to_string())
                         .collect())
                 }
@@ -59,7 +59,7 @@ pub mod environment_variable {
             match self.get_list(name, delimiter) {
                 Ok(l) => {
                     let mut ll = l;
-                    ll.insert(to, value.to_string());
+                    ll.insert(to.min(ll.len()), value.to_string());
                     self.set_list(name, &ll, delimiter)
                 }
                 Err(s) => Err(s),
@@ -109,6 +109,102 @@ pub mod environment_variable {
         fn remove_path_from(&mut self, path: &Path) -> Result<(), String>;
     }
 
+    /// Environment variables held in memory, e.g., to compute the result of an import
+    /// without applying it.
+    pub mod memory {
+        use std::path::Path;
+
+        use crate::envvar::environment_variable::EnvironmentVariable;
+        use crate::envvar::environment_variable::env::{PATH, PATH_DELIMITER};
+
+        pub struct MemoryEnvironment {
+            vars: Vec<(String, String)>,
+        }
+
+        impl MemoryEnvironment {
+            pub fn new(vars: Vec<(String, String)>) -> Self {
+                Self { vars }
+            }
+
+            /// Copies the environment of the current process.
+            pub fn from_process() -> Self {
+                Self::new(std::env::vars().collect())
+            }
+
+            pub fn vars(&self) -> &Vec<(String, String)> {
+                &self.vars
+            }
+        }
+
+        impl EnvironmentVariable for MemoryEnvironment {
+            fn list(&self) -> Result<Vec<(String, String)>, String> {
+                Ok(self.vars.clone())
+            }
+
+            fn get(&self, name: &String) -> Result<String, String> {
+                match self.vars.iter().find(|(k, _)| k == name) {
+                    Some((_, v)) => Ok(v.to_string()),
+                    None => Err(format!("key not found: {}", name)),
+                }
+            }
+
+            fn set(&mut self, name: &String, value: &String) -> Result<(), String> {
+                match self.vars.iter_mut().find(|(k, _)| k == name) {
+                    Some(e) => e.1 = value.to_string(),
+                    None => self.vars.push((name.to_string(), value.to_string())),
+                }
+                Ok(())
+            }
+
+            fn delete(&mut self, name: &String) -> Result<(), String> {
+                match self.vars.iter().position(|(k, _)| k == name) {
+                    Some(i) => {
+                        self.vars.remove(i);
+                        Ok(())
+                    }
+                    None => Err(format!("key not found: {}", name)),
+                }
+            }
+
+            fn get_path(&self) -> Result<Vec<String>, String> {
+                self.get_list(&PATH.to_string(), &PATH_DELIMITER.to_string())
+            }
+
+            fn set_path(&mut self, paths: &Vec<String>) -> Result<(), String> {
+                self.set_list(&PATH.to_string(), paths, &PATH_DELIMITER.to_string())
+            }
+
+            fn append_path(&mut self, path: &Path) -> Result<(), String> {
+                self.append_list(
+                    &PATH.to_string(),
+                    &path.to_string_lossy().to_string(),
+                    &PATH_DELIMITER.to_string(),
+                )
+            }
+
+            fn insert_path(&mut self, path: &Path, to: usize) -> Result<(), String> {
+                self.insert_list(
+                    &PATH.to_string(),
+                    &path.to_string_lossy().to_string(),
+                    to,
+                    &PATH_DELIMITER.to_string(),
+                )
+            }
+
+            fn remove_path(&mut self, from: usize) -> Result<(), String> {
+                self.remove_list(&PATH.to_string(), from, &PATH_DELIMITER.to_string())
+            }
+
+            fn remove_path_from(&mut self, path: &Path) -> Result<(), String> {
+                self.remove_list_from(
+                    &PATH.to_string(),
+                    &path.to_string_lossy().to_string(),
+                    &PATH_DELIMITER.to_string(),
+                )
+            }
+        }
+    }
+
     #[cfg(target_os = "windows")]
     pub mod env {
         use std::path::Path;
@@ -474,10 +570,7 @@ pub mod environment_variable {
 
     #[cfg(target_os = "linux")]
     pub mod env {
-        use libc::{getenv, setenv};
         use std::env;
-        use std::ffi::CStr;
-        use std::os::raw::c_char;
         use std::path::Path;
 
         use crate::envvar::environment_variable::EnvironmentVariable;
@@ -501,9 +594,9 @@ pub mod environment_variable {
                 if self.shellrc.is_none() {
                     self.shellrc = Some({
                         let mut d = ShellRunCommandFileData::new(shell);
-                        // set current environment variables
+                        // only the changes to the current environment are written
                         for (k, v) in self.list().unwrap().iter() {
-                            d.add(k, v);
+                            d.load(k, v);
                         }
                         d
                     });
@@ -516,21 +609,6 @@ pub mod environment_variable {
                 self.shellrc.as_ref().unwrap().write(filepath)
             }
 
-            fn string_to_i8vec(s: &String) -> Vec<i8> {
-                let mut ws: Vec<i8> = s.chars().map(|e| e as i8).collect();
-                // terminal character
-                ws.push(0x00);
-
-                ws
-            }
-
-            fn c_char_to_str<'a>(lpsz: *const c_char) -> Result<&'a str, String> {
-                match unsafe { CStr::from_ptr(lpsz) }.to_str() {
-                    Ok(s) => Ok(s),
-                    Err(e) => Err(e.to_string()),
-                }
-            }
-
             fn write(&self, output_path: &String) {
                 let s = match &self.shellrc {
                     Some(e) => e,
@@ -558,16 +636,19 @@ pub mod environment_variable {
             }
 
             fn get(&self, name: &String) -> Result<String, String> {
-                let name_i8vec = Self::string_to_i8vec(name);
-                let v: *mut c_char = unsafe { getenv(name_i8vec.as_ptr()) };
-                match Self::c_char_to_str(v) {
-                    Ok(s) => Ok(s.to_string()),
-                    Err(e) => Err(e),
+                // values already applied to the run control file take precedence
+                if let Some(s) = &self.shellrc {
+                    return s.get(name);
+                }
+
+                match env::var(name) {
+                    Ok(s) => Ok(s),
+                    Err(e) => Err(format!("{}: {}", e, name)),
                 }
             }
 
             fn set(&mut self, name: &String, value: &String) -> Result<(), String> {
-                (&mut self.shellrc).as_mut().unwrap().set(name, value);
+                (&mut self.shellrc).as_mut().unwrap().add(name, value);
                 Ok(())
             }
 
@@ -0,0 +1,310 @@
+pub mod format {
+    use std::fs;
+    use std::path::Path;
+
+    use crate::docker::docker;
+    use crate::dotenv::dotenv;
+    use crate::envvar::environment_variable::EnvironmentVariable;
+    use crate::json::config::{self, Config, Metadata, ValueDetail};
+    use crate::kubernetes::kubernetes;
+    use crate::portable::portable::{self, Translation};
+    use crate::utils;
+
+    /// Formats accepted by `--format` and `--input-format`.
+    pub const FORMATS: [&str; 6] = [
+        "json",
+        "dotenv",
+        "docker",
+        "compose",
+        "kubernetes",
+        "k8s-env",
+    ];
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum Format {
+        Json,
+        Dotenv,
+        /// Docker `--env-file`
+        Docker,
+        /// `environment:` of a Compose file
+        Compose,
+        /// ConfigMap and Secret manifests
+        Kubernetes,
+        /// `env:` of a container
+        KubernetesEnv,
+    }
+
+    /// Returns the format named `name`, or the one guessed from the name of `path`.
+    pub fn resolve(name: Option<&str>, path: &Path) -> Result<Format, String> {
+        match name {
+            Some("json") => Ok(Format::Json),
+            Some("dotenv" | "env") => Ok(Format::Dotenv),
+            Some("docker" | "env-file") => Ok(Format::Docker),
+            Some("compose" | "docker-compose") => Ok(Format::Compose),
+            Some("kubernetes" | "k8s") => Ok(Format::Kubernetes),
+            Some("k8s-env") => Ok(Format::KubernetesEnv),
+            Some(s) => Err(format!(
+                "unknown format: {} (supported: {})",
+                s,
+                FORMATS.join(", ")
+            )),
+            None => Ok(guess(path)),
+        }
+    }
+
+    /// Guesses the format from the name of `path`, e.g., `.env`, `.env.local`, `prod.env`
+    /// or `compose.yaml`. Docker env-files look like `.env` files, so they are never guessed.
+    pub fn guess(path: &Path) -> Format {
+        let name = match path.file_name() {
+            Some(s) => s.to_string_lossy().to_lowercase(),
+            None => return Format::Json,
+        };
+
+        if name == ".env" || name.starts_with(".env.") || name.ends_with(".env") {
+            Format::Dotenv
+        } else if name.ends_with(".yml") || name.ends_with(".yaml") {
+            Format::Compose
+        } else {
+            Format::Json
+        }
+    }
+
+    fn read_file(path: &Path) -> Result<String, String> {
+        if !path.exists() {
+            return Err(format!("file not found. path: {}", path.to_string_lossy()));
+        }
+
+        match fs::read_to_string(path) {
+            Ok(s) => Ok(s),
+            Err(e) => Err(format!("{}: {}", e, path.to_string_lossy())),
+        }
+    }
+
+    fn write_file(path: &Path, content: &str) -> Result<(), String> {
+        match fs::write(path, content) {
+            Ok(_) => Ok(()),
+            Err(e) => Err(format!("{}: {}", e, path.to_string_lossy())),
+        }
+    }
+
+    fn print_warnings(warnings: &[String]) {
+        for w in warnings.iter() {
+            eprintln!("warning: {}", w);
+        }
+    }
+
+    /// Reads a config in `format`, or the one guessed from the name of `path`.
+    pub fn read_config(path: &Path, format: Option<&str>) -> Result<Config, String> {
+        match resolve(format, path)? {
+            Format::Json => config::read_config(path),
+            _ => parse_config(&read_file(path)?, path, format),
+        }
+    }
+
+    /// Parses `content` read from `path` as `read_config` does.
+    pub fn parse_config(
+        content: &str,
+        path: &Path,
+        format: Option<&str>,
+    ) -> Result<Config, String> {
+        let env = std::env::vars().collect::<Vec<(String, String)>>();
+        let (config, warnings) = match resolve(format, path)? {
+            Format::Json => match serde_json::from_str::<Config>(content) {
+                Ok(c) => (c, Vec::new()),
+                Err(e) => return Err(format!("{}: {}", e, path.to_string_lossy())),
+            },
+            Format::Dotenv => dotenv::parse(content, &env)?,
+            Format::Docker => docker::parse_env_file(content, &env)?,
+            Format::Compose => docker::parse_compose(content, &env)?,
+            Format::Kubernetes | Format::KubernetesEnv => kubernetes::parse_manifests(content)?,
+        };
+        print_warnings(&warnings);
+
+        Ok(config)
+    }
+
+    /// Reads a config to import, warning about lists and paths of another platform,
+    /// or converting them as `translation` says.
+    pub fn read_config_to_import(
+        path: &Path,
+        format: Option<&str>,
+        translation: &Translation,
+    ) -> Result<Config, String> {
+        let (config, warnings) = portable::prepare(read_config(path, format)?, translation);
+        print_warnings(&warnings);
+
+        Ok(config)
+    }
+
+    /// Parses a config to import from `content`, as `read_config_to_import` does.
+    pub fn parse_config_to_import(
+        content: &str,
+        path: &Path,
+        format: Option<&str>,
+        translation: &Translation,
+    ) -> Result<Config, String> {
+        let (config, warnings) =
+            portable::prepare(parse_config(content, path, format)?, translation);
+        print_warnings(&warnings);
+
+        Ok(config)
+    }
+
+    pub fn config_to_string(config: &Config, format: Format) -> Result<String, String> {
+        let (s, warnings) = match format {
+            Format::Json => return config::config_to_string(config),
+            Format::Dotenv => dotenv::config_to_string(config),
+            Format::Docker => docker::config_to_env_file(config),
+            Format::Compose => docker::config_to_compose(config)?,
+            Format::Kubernetes => kubernetes::config_to_manifests(config, kubernetes::NAME)?,
+            Format::KubernetesEnv => kubernetes::config_to_env_list(config, kubernetes::NAME)?,
+        };
+        print_warnings(&warnings);
+
+        Ok(s)
+    }
+
+    /// Writes a config in `format`, or the one guessed from the name of `path`.
+    pub fn write_config(config: &Config, path: &Path, format: Option<&str>) -> Result<(), String> {
+        match resolve(format, path)? {
+            Format::Json => config::write_config(config, path),
+            f => write_file(path, &config_to_string(config, f)?),
+        }
+    }
+
+    /// Prints a config in `format`, JSON by default.
+    pub fn print_config(config: &Config, format: Option<&str>) -> Result<(), String> {
+        let format = resolve(format.or(Some("json")), Path::new(""))?;
+        print!("{}", config_to_string(config, format)?);
+        if format == Format::Json {
+            println!();
+        }
+
+        Ok(())
+    }
+
+    /// Returns whether `name` matches `pattern`, where `*` is any string and `?` is any character.
+    pub fn matches(pattern: &str, name: &str) -> bool {
+        let pattern = pattern.chars().collect::<Vec<char>>();
+        let name = name.chars().collect::<Vec<char>>();
+
+        // matched[j]: the pattern so far matches the first j characters of the name
+        let mut matched = vec![false; name.len() + 1];
+        matched[0] = true;
+        for p in pattern.iter() {
+            let mut next = vec![false; name.len() + 1];
+            for j in 0..=name.len() {
+                next[j] = match p {
+                    '*' => matched[j] || (j > 0 && next[j - 1]),
+                    '?' => j > 0 && matched[j - 1],
+                    c => j > 0 && matched[j - 1] && name[j - 1] == *c,
+                };
+            }
+            matched = next;
+        }
+
+        matched[name.len()]
+    }
+
+    /// Describes this machine for the config exported here.
+    fn metadata() -> Metadata {
+        #[cfg(target_os = "linux")]
+        let shell = crate::shellrc::detect::from_env().unwrap_or_default();
+        #[cfg(not(target_os = "linux"))]
+        let shell = "";
+
+        Metadata {
+            platform: std::env::consts::OS.to_string(),
+            host: utils::utils::hostname(),
+            shell: shell.to_string(),
+            created: utils::utils::now(),
+            version: utils::utils::get_version().to_string(),
+        }
+    }
+
+    /// Writes the variables of `envvar` in `format`, only the ones matching a pattern
+    /// of `filter` unless it is empty, for the platform of `translation`.
+    pub fn export_envvar<T: EnvironmentVariable>(
+        envvar: &T,
+        path: &Path,
+        format: Option<&str>,
+        filter: &[&str],
+        translation: &Translation,
+    ) -> Result<(), String> {
+        let list = envvar
+            .list()?
+            .into_iter()
+            .filter(|(k, _)| filter.is_empty() || filter.iter().any(|p| matches(p, k)))
+            .collect::<Vec<(String, String)>>();
+
+        let mut config = config::vars_to_config(list);
+        config.metadata = Some(metadata());
+        let (config, warnings) = portable::export(config, portable::current(), translation);
+        print_warnings(&warnings);
+
+        match resolve(format, path)? {
+            Format::Json => config::write_config(&config, path),
+            Format::Dotenv => {
+                let list = config
+                    .data
+                    .into_iter()
+                    .map(|e| (e.key, e.value))
+                    .collect::<Vec<(String, String)>>();
+                let (s, warnings) = dotenv::vars_to_string(&list);
+                print_warnings(&warnings);
+                write_file(path, &s)
+            }
+            f => {
+                let data = config
+                    .data
+                    .into_iter()
+                    .map(|e| ValueDetail {
+                        key: e.key,
+                        value: e.value,
+                        overwrite: true,
+                        delimiter: "".to_string(),
+                        insert: -1,
+                        secret: false,
+                    })
+                    .collect::<Vec<ValueDetail>>();
+                let config = Config {
+                    version: 1,
+                    data,
+                    metadata: None,
+                };
+                write_file(path, &config_to_string(&config, f)?)
+            }
+        }
+    }
+
+    /// Reads the config at `input` and writes it to `output`, converting the format and
+    /// the platform of `translation`.
+    pub fn convert(
+        input: &Path,
+        input_format: Option<&str>,
+        output: &Path,
+        format: Option<&str>,
+        translation: &Translation,
+    ) -> Result<(), String> {
+        let config = read_config(input, input_format)?;
+        let from = portable::source_style(&config).unwrap_or(portable::current());
+        let (config, warnings) = portable::export(config, from, translation);
+        print_warnings(&warnings);
+
+        write_config(&config, output, format)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::format::format::matches;
+
+    #[test]
+    fn test_matches() {
+        assert!(matches("APP_*", "APP_PORT"));
+        assert!(matches("*PATH", "PATH"));
+        assert!(matches("LC_?", "LC_A"));
+        assert!(!matches("APP_*", "MY_APP_PORT"));
+        assert!(!matches("LC_?", "LC_ALL"));
+    }
+}
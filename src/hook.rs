@@ -0,0 +1,383 @@
+pub mod hook {
+    use std::path::{Path, PathBuf};
+
+    use serde::{Deserialize, Serialize};
+
+    use crate::activate::activate::{Frame, record_changes};
+    use crate::envvar::environment_variable::EnvironmentVariable;
+    use crate::envvar::environment_variable::memory::MemoryEnvironment;
+    use crate::format::format;
+    use crate::hook::trust;
+    use crate::json::config::{self, Config};
+    use crate::portable::portable::Translation;
+    use crate::settings::settings::Settings;
+    use crate::shellrc::detect::resolve_session_shell;
+    use crate::shellrc::shellrc::{
+        ShellRunCommandFile, ShellRunCommandFileData, canonical_shell_name, hook_script,
+    };
+
+    /// The config applied while the current directory is inside the directory holding it.
+    pub const CONFIG_FILE: &str = ".envvar.json";
+    /// Holds the config loaded by the hook in the environment of the shell session.
+    pub const STATE_KEY: &str = "ENVVAR_DIR";
+
+    /// The loaded config, its hash and the values it replaced.
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    pub struct DirState {
+        pub hash: String,
+        pub frame: Frame,
+    }
+
+    /// The config to load, with the path and hash of the file it was read from.
+    pub struct Target<'a> {
+        pub path: &'a str,
+        pub hash: &'a str,
+        pub config: &'a Config,
+    }
+
+    pub fn parse_state(s: &str) -> Result<Option<DirState>, String> {
+        if s.is_empty() {
+            return Ok(None);
+        }
+
+        match serde_json::from_str::<DirState>(s) {
+            Ok(d) => Ok(Some(d)),
+            Err(e) => Err(format!("invalid {}: {}", STATE_KEY, e)),
+        }
+    }
+
+    /// Returns the nearest [`CONFIG_FILE`] in `dir` or its ancestors.
+    pub fn find_config(dir: &Path) -> Option<PathBuf> {
+        dir.ancestors()
+            .map(|d| d.join(CONFIG_FILE))
+            .find(|p| p.is_file())
+    }
+
+    /// Builds the commands that unload the config in `state` and load `target`.
+    /// Nothing is built when `target` is the config that is already loaded.
+    pub fn build_hook_env(
+        shell: &str,
+        before: &Vec<(String, String)>,
+        state: Option<&DirState>,
+        target: Option<&Target>,
+    ) -> Result<String, String> {
+        let is_loaded = match (state, target) {
+            (Some(s), Some(t)) => s.frame.name == t.path && s.hash == t.hash,
+            (None, None) => true,
+            _ => false,
+        };
+        if is_loaded {
+            return Ok(String::new());
+        }
+
+        // the values of the variables before the config in `state` was loaded
+        let mut reverted = MemoryEnvironment::new(before.clone());
+        if let Some(s) = state {
+            for v in s.frame.saved.iter() {
+                match &v.value {
+                    Some(value) => reverted.set(&v.key, value)?,
+                    None => {
+                        let _ = reverted.delete(&v.key);
+                    }
+                }
+            }
+        }
+
+        let mut after = MemoryEnvironment::new(reverted.vars().clone());
+        if let Some(t) = target {
+            config::apply_config(t.config, false, false, &mut after)?;
+        }
+
+        let mut data = ShellRunCommandFileData::new(shell);
+        record_changes(before, after.vars(), &mut data);
+
+        match target {
+            Some(t) => {
+                // the values to restore on unload are the ones without the previous config
+                let mut scratch = ShellRunCommandFileData::new(shell);
+                let saved = record_changes(reverted.vars(), after.vars(), &mut scratch);
+
+                let state = DirState {
+                    hash: t.hash.to_string(),
+                    frame: Frame {
+                        name: t.path.to_string(),
+                        saved,
+                    },
+                };
+                match serde_json::to_string(&state) {
+                    Ok(s) => data.add(&STATE_KEY.to_string(), &s),
+                    Err(e) => return Err(e.to_string()),
+                }
+            }
+            None => data.unset(&STATE_KEY.to_string()),
+        }
+
+        data.build_with_header(&String::new())
+    }
+
+    /// Prints the prompt hook for the shell given as the operand.
+    pub fn hook(settings: &Settings) -> Result<(), String> {
+        let name = match (settings.operands.first(), settings.shell_rc.shell) {
+            (Some(s), _) => *s,
+            (None, Some(s)) => s,
+            (None, None) => return Err("no shell specified".to_string()),
+        };
+        let shell = match canonical_shell_name(name) {
+            Some(s) => s,
+            None => return Err(format!("unknown shell name: {}", name)),
+        };
+
+        let program = match std::env::current_exe() {
+            Ok(p) => p.to_string_lossy().to_string(),
+            Err(e) => return Err(e.to_string()),
+        };
+
+        println!("{}", hook_script(shell, &program)?);
+
+        Ok(())
+    }
+
+    /// Prints the commands that bring the session in line with the current directory.
+    pub fn hook_env(settings: &Settings) -> Result<(), String> {
+        let (shell, _) = resolve_session_shell(settings.shell_rc.shell)?;
+        let state = parse_state(&std::env::var(STATE_KEY).unwrap_or_default())?;
+
+        let found = match std::env::current_dir() {
+            Ok(d) => find_config(&d),
+            Err(_) => None,
+        };
+        // the hash and the config come from the same read, so the file cannot change between
+        let found = match found {
+            Some(p) => {
+                let p = match p.canonicalize() {
+                    Ok(p) => p,
+                    Err(e) => return Err(format!("{}: {}", e, p.to_string_lossy())),
+                };
+                let content = match std::fs::read_to_string(&p) {
+                    Ok(s) => s,
+                    Err(e) => return Err(format!("{}: {}", e, p.to_string_lossy())),
+                };
+                let path = p.to_string_lossy().to_string();
+                let hash = trust::hash(content.as_bytes());
+                if trust::is_allowed(&path, &hash)? {
+                    Some((path, hash, content))
+                } else {
+                    eprintln!(
+                        "envvar: {} is not allowed. Run `envvar allow` to trust its content.",
+                        path
+                    );
+                    None
+                }
+            }
+            None => None,
+        };
+
+        let config = match &found {
+            Some((path, _, content)) => Some(format::parse_config_to_import(
+                content,
+                Path::new(path),
+                Some("json"),
+                &Translation::from_settings(settings)?,
+            )?),
+            None => None,
+        };
+        let target = match (&found, &config) {
+            (Some((path, hash, _)), Some(config)) => Some(Target { path, hash, config }),
+            _ => None,
+        };
+
+        let before = std::env::vars().collect::<Vec<(String, String)>>();
+        let script = build_hook_env(shell, &before, state.as_ref(), target.as_ref())?;
+        if script.is_empty() {
+            return Ok(());
+        }
+
+        if let Some(s) = &state {
+            eprintln!("envvar: unloading {}", s.frame.name);
+        }
+        if let Some(t) = &target {
+            eprintln!("envvar: loading {}", t.path);
+        }
+        println!("{}", script);
+
+        Ok(())
+    }
+
+    fn config_path(settings: &Settings) -> Result<PathBuf, String> {
+        let path = match settings.operands.first() {
+            Some(s) if Path::new(s).is_dir() => Path::new(s).join(CONFIG_FILE),
+            Some(s) => PathBuf::from(s),
+            None => {
+                let dir = match std::env::current_dir() {
+                    Ok(d) => d,
+                    Err(e) => return Err(e.to_string()),
+                };
+                match find_config(&dir) {
+                    Some(p) => p,
+                    None => return Err(format!("{} not found", CONFIG_FILE)),
+                }
+            }
+        };
+
+        match path.canonicalize() {
+            Ok(p) => Ok(p),
+            Err(e) => Err(format!("{}: {}", e, path.to_string_lossy())),
+        }
+    }
+
+    pub fn allow(settings: &Settings) -> Result<(), String> {
+        let path = config_path(settings)?;
+        let hash = trust::file_hash(&path)?;
+        trust::allow(&path.to_string_lossy(), &hash)?;
+        println!("allowed: {}", path.to_string_lossy());
+
+        Ok(())
+    }
+
+    pub fn deny(settings: &Settings) -> Result<(), String> {
+        let path = config_path(settings)?;
+        trust::deny(&path.to_string_lossy())?;
+        println!("denied: {}", path.to_string_lossy());
+
+        Ok(())
+    }
+}
+
+/// The files allowed to be loaded by the hook, each with the hash of its trusted content.
+pub mod trust {
+    use std::fs;
+    use std::path::Path;
+
+    use serde::{Deserialize, Serialize};
+    use sha2::{Digest, Sha256};
+
+    use crate::utils;
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    pub struct Entry {
+        pub path: String,
+        pub hash: String,
+    }
+
+    fn store_path() -> String {
+        format!("{}/envvar/allow.json", utils::utils::data_home())
+    }
+
+    /// Returns the SHA-256 hash of `content`.
+    pub fn hash(content: &[u8]) -> String {
+        Sha256::digest(content)
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    /// Returns the SHA-256 hash of the content of the file at `path`.
+    pub fn file_hash(path: &Path) -> Result<String, String> {
+        match fs::read(path) {
+            Ok(b) => Ok(hash(&b)),
+            Err(e) => Err(format!("{}: {}", e, path.to_string_lossy())),
+        }
+    }
+
+    fn read_store() -> Result<Vec<Entry>, String> {
+        let path = store_path();
+        if !Path::new(&path).exists() {
+            return Ok(Vec::new());
+        }
+
+        let s = match fs::read_to_string(&path) {
+            Ok(s) => s,
+            Err(e) => return Err(format!("{}: {}", e, path)),
+        };
+        match serde_json::from_str::<Vec<Entry>>(&s) {
+            Ok(e) => Ok(e),
+            Err(e) => Err(format!("{}: {}", e, path)),
+        }
+    }
+
+    fn write_store(entries: &Vec<Entry>) -> Result<(), String> {
+        let path = store_path();
+        if let Some(parent) = Path::new(&path).parent()
+            && let Err(e) = fs::create_dir_all(parent)
+        {
+            return Err(e.to_string());
+        }
+
+        let s = match serde_json::to_string_pretty(entries) {
+            Ok(s) => s,
+            Err(e) => return Err(e.to_string()),
+        };
+        match fs::write(&path, s) {
+            Ok(_) => Ok(()),
+            Err(e) => Err(format!("{}: {}", e, path)),
+        }
+    }
+
+    /// Returns true if the content of `path` with `hash` has been allowed.
+    pub fn is_allowed(path: &str, hash: &str) -> Result<bool, String> {
+        Ok(read_store()?
+            .iter()
+            .any(|e| e.path == path && e.hash == hash))
+    }
+
+    pub fn allow(path: &str, hash: &str) -> Result<(), String> {
+        let mut entries = read_store()?;
+        entries.retain(|e| e.path != path);
+        entries.push(Entry {
+            path: path.to_string(),
+            hash: hash.to_string(),
+        });
+
+        write_store(&entries)
+    }
+
+    pub fn deny(path: &str) -> Result<(), String> {
+        let mut entries = read_store()?;
+        entries.retain(|e| e.path != path);
+
+        write_store(&entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::activate::activate::{Frame, SavedValue};
+    use crate::hook::hook::{DirState, Target, build_hook_env};
+    use crate::json::config::Config;
+    use crate::utils::utils::vars;
+
+    #[test]
+    fn test_hook_env_load_and_unload() {
+        let json = r#"{"version":1,"data":[{"key":"FOO","value":"foo","overwrite":true}]}"#;
+        let config = serde_json::from_str::<Config>(json).unwrap();
+        let before = vars(&[("HOME", "/home/user")]);
+        let target = Target {
+            path: "/work/.envvar.json",
+            hash: "0123",
+            config: &config,
+        };
+
+        let script = build_hook_env("bash", &before, None, Some(&target)).unwrap();
+        assert!(script.lines().any(|l| l == "export FOO=foo"));
+        assert!(script.lines().any(|l| l.starts_with("export ENVVAR_DIR=")));
+
+        let state = DirState {
+            hash: "0123".to_string(),
+            frame: Frame {
+                name: "/work/.envvar.json".to_string(),
+                saved: vec![SavedValue {
+                    key: "FOO".to_string(),
+                    value: None,
+                }],
+            },
+        };
+        let loaded = vars(&[("HOME", "/home/user"), ("FOO", "foo")]);
+        let script = build_hook_env("bash", &loaded, Some(&state), Some(&target)).unwrap();
+        assert!(script.is_empty());
+
+        let script = build_hook_env("bash", &loaded, Some(&state), None).unwrap();
+        assert!(script.lines().any(|l| l == "unset FOO"));
+        assert!(script.lines().any(|l| l == "unset ENVVAR_DIR"));
+        assert!(!script.contains("HOME"));
+    }
+}
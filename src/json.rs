@@ -8,12 +8,9 @@ pub mod config {
 
     use serde::{Deserialize, Serialize};
 
-    use crate::envvar::{
-        self,
-        environment_variable::{
-            env::{PATH, PATH_DELIMITER},
-            EnvironmentVariable,
-        },
+    use crate::envvar::environment_variable::{
+        env::{PATH, PATH_DELIMITER},
+        EnvironmentVariable,
     };
 
     fn default_overwrite() -> bool {
@@ -28,7 +25,11 @@ pub mod config {
         -1
     }
 
-    #[derive(Serialize, Deserialize, Debug)]
+    fn is_false(b: &bool) -> bool {
+        !*b
+    }
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
     pub struct ValueDetail {
         pub key: String,
         pub value: String,
@@ -38,12 +39,34 @@ pub mod config {
         pub delimiter: String,
         #[serde(default = "default_append")]
         pub insert: i32,
+        /// Kept in a Secret instead of a ConfigMap on Kubernetes.
+        #[serde(default, skip_serializing_if = "is_false")]
+        pub secret: bool,
+    }
+
+    /// Where and when a config was exported.
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    pub struct Metadata {
+        /// `std::env::consts::OS` of the machine, e.g., `linux` or `windows`
+        pub platform: String,
+        #[serde(default)]
+        pub host: String,
+        #[serde(default)]
+        pub shell: String,
+        /// RFC 3339 time in UTC
+        #[serde(default)]
+        pub created: String,
+        /// Version of envvar that exported the config
+        #[serde(default)]
+        pub version: String,
     }
 
     #[derive(Serialize, Deserialize, Debug)]
     pub struct Config {
         pub version: u32,
         pub data: Vec<ValueDetail>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub metadata: Option<Metadata>,
     }
 
     fn create_value(key: String, value: String) -> ValueDetail {
@@ -58,29 +81,33 @@ pub mod config {
             overwrite: default_overwrite(),
             delimiter,
             insert: default_append(),
+            secret: false,
         }
     }
 
-    pub fn export_envvar(filepath: &Path) -> Result<(), String> {
+    /// Converts the variables into a config, as exported by `--export`.
+    pub fn vars_to_config(list: Vec<(String, String)>) -> Config {
         let mut data = Config {
             version: 1,
             data: Vec::new(),
+            metadata: None,
         };
+        for (v, d) in list {
+            data.data.push(create_value(v, d));
+        }
 
-        let envvar = envvar::environment_variable::env::Environment::new();
-        match envvar.list() {
-            Ok(list) => {
-                for (v, d) in list {
-                    data.data.push(create_value(v, d));
-                }
-            }
-            Err(e) => return Err(e),
+        data
+    }
+
+    pub fn config_to_string(config: &Config) -> Result<String, String> {
+        match serde_json::to_string_pretty(config) {
+            Ok(s) => Ok(s),
+            Err(e) => Err(e.to_string()),
         }
+    }
 
-        let json = match serde_json::to_string_pretty(&data) {
-            Ok(s) => s,
-            Err(e) => return Err(e.to_string()),
-        };
+    pub fn write_config(config: &Config, filepath: &Path) -> Result<(), String> {
+        let json = config_to_string(config)?;
         let mut file = match File::create(filepath) {
             Ok(f) => f,
             Err(e) => return Err(e.to_string()),
@@ -94,11 +121,7 @@ pub mod config {
         Ok(())
     }
 
-    pub fn import_envvar(
-        filepath: &Path,
-        dry_run: bool,
-        envvar: &mut envvar::environment_variable::env::Environment,
-    ) -> Result<(), String> {
+    pub fn read_config(filepath: &Path) -> Result<Config, String> {
         if !filepath.exists() {
             return Err(format!(
                 "file not found. path: {}",
@@ -116,11 +139,19 @@ pub mod config {
             Err(e) => return Err(format!("{}", e.to_string())),
         };
 
-        let config = match serde_json::from_str::<Config>(json.as_str()) {
-            Ok(c) => c,
-            Err(e) => return Err(format!("{}", e.to_string())),
-        };
+        match serde_json::from_str::<Config>(json.as_str()) {
+            Ok(c) => Ok(c),
+            Err(e) => Err(format!("{}", e.to_string())),
+        }
+    }
 
+    /// Applies `config` to `envvar`, printing the planned changes if `preview` is set.
+    pub fn apply_config<T: EnvironmentVariable>(
+        config: &Config,
+        dry_run: bool,
+        preview: bool,
+        envvar: &mut T,
+    ) -> Result<(), String> {
         let mut current = HashMap::<String, String>::new();
         match envvar.list() {
             Ok(l) => {
@@ -155,8 +186,7 @@ pub mod config {
             new_values.push(&v);
         }
 
-        // preview
-        {
+        if preview {
             println!("new:");
             for e in new_values.iter() {
                 println!("        {}", e.key);
@@ -225,6 +255,7 @@ mod tests {
         let mut data = Config {
             version: 1,
             data: Vec::new(),
+            metadata: None,
         };
 
         data.data.push(ValueDetail {
@@ -233,6 +264,7 @@ mod tests {
             overwrite: false,
             delimiter: "|".to_string(),
             insert: -1,
+            secret: false,
         });
 
         data.data.push(ValueDetail {
@@ -241,6 +273,7 @@ mod tests {
             overwrite: true,
             delimiter: "()".to_string(),
             insert: 1,
+            secret: false,
         });
 
         let to_string_result = serde_json::to_string(&data);
@@ -0,0 +1,535 @@
+pub mod kubernetes {
+    use base64::Engine;
+    use base64::engine::general_purpose::STANDARD;
+    use serde::Deserialize;
+    use serde_yaml::{Mapping, Value};
+
+    use crate::json::config::{Config, ValueDetail};
+
+    /// Name of the ConfigMap and the Secret, which the `env:` of a container refers to.
+    pub const NAME: &str = "envvar";
+
+    fn entry(key: &str, value: &str, secret: bool) -> ValueDetail {
+        ValueDetail {
+            key: key.to_string(),
+            value: value.to_string(),
+            overwrite: true,
+            delimiter: "".to_string(),
+            insert: -1,
+            secret,
+        }
+    }
+
+    fn string(s: &str) -> Value {
+        Value::String(s.to_string())
+    }
+
+    fn mapping(list: Vec<(&str, Value)>) -> Value {
+        let mut result = Mapping::new();
+        for (k, v) in list {
+            result.insert(string(k), v);
+        }
+        Value::Mapping(result)
+    }
+
+    fn is_valid_key(key: &str) -> bool {
+        !key.is_empty()
+            && key
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "-._".contains(c))
+    }
+
+    /// Returns the entries that can be written as plain values, the last one for each name.
+    fn plain_entries(config: &Config) -> (Vec<&ValueDetail>, Vec<String>) {
+        let mut result: Vec<&ValueDetail> = Vec::new();
+        let mut warnings: Vec<String> = Vec::new();
+
+        for e in config.data.iter() {
+            if !is_valid_key(&e.key) {
+                warnings.push(format!("{}: invalid name for Kubernetes, skipped", e.key));
+                continue;
+            }
+            if !e.overwrite && !e.delimiter.is_empty() {
+                warnings.push(format!(
+                    "{}: extending a list cannot be expressed on Kubernetes, skipped",
+                    e.key
+                ));
+                continue;
+            }
+            result.retain(|r| r.key != e.key);
+            result.push(e);
+        }
+
+        (result, warnings)
+    }
+
+    fn to_yaml(documents: &[Value]) -> Result<String, String> {
+        let mut result: Vec<String> = Vec::new();
+        for d in documents.iter() {
+            match serde_yaml::to_string(d) {
+                Ok(s) => result.push(s),
+                Err(e) => return Err(e.to_string()),
+            }
+        }
+
+        Ok(result.join("---\n"))
+    }
+
+    /// Writes a config as a ConfigMap, and a Secret holding the entries flagged secret.
+    pub fn config_to_manifests(
+        config: &Config,
+        name: &str,
+    ) -> Result<(String, Vec<String>), String> {
+        let (entries, warnings) = plain_entries(config);
+        let (secrets, plain): (Vec<&ValueDetail>, Vec<&ValueDetail>) =
+            entries.into_iter().partition(|e| e.secret);
+
+        let metadata = || mapping(vec![("name", string(name))]);
+        let mut documents: Vec<Value> = Vec::new();
+        if !plain.is_empty() || secrets.is_empty() {
+            let data = plain
+                .iter()
+                .map(|e| (e.key.as_str(), string(&e.value)))
+                .collect();
+            documents.push(mapping(vec![
+                ("apiVersion", string("v1")),
+                ("kind", string("ConfigMap")),
+                ("metadata", metadata()),
+                ("data", mapping(data)),
+            ]));
+        }
+        if !secrets.is_empty() {
+            let data = secrets
+                .iter()
+                .map(|e| (e.key.as_str(), string(&STANDARD.encode(&e.value))))
+                .collect();
+            documents.push(mapping(vec![
+                ("apiVersion", string("v1")),
+                ("kind", string("Secret")),
+                ("metadata", metadata()),
+                ("type", string("Opaque")),
+                ("data", mapping(data)),
+            ]));
+        }
+
+        Ok((to_yaml(&documents)?, warnings))
+    }
+
+    /// Writes a config as the `env:` of a container. Entries flagged secret refer to
+    /// the Secret `name` written by `config_to_manifests`.
+    pub fn config_to_env_list(
+        config: &Config,
+        name: &str,
+    ) -> Result<(String, Vec<String>), String> {
+        let (entries, warnings) = plain_entries(config);
+
+        let mut list: Vec<Value> = Vec::new();
+        for e in entries.iter() {
+            let value = match e.secret {
+                true => (
+                    "valueFrom",
+                    mapping(vec![(
+                        "secretKeyRef",
+                        mapping(vec![("name", string(name)), ("key", string(&e.key))]),
+                    )]),
+                ),
+                // `$(NAME)` refers to another variable of the container, and `$$` is `$`
+                false => ("value", string(&e.value.replace('$', "$$"))),
+            };
+            list.push(mapping(vec![("name", string(&e.key)), value]));
+        }
+
+        Ok((
+            to_yaml(&[mapping(vec![("env", Value::Sequence(list))])])?,
+            warnings,
+        ))
+    }
+
+    fn get<'a>(value: &'a Value, path: &[&str]) -> Option<&'a Value> {
+        path.iter().try_fold(value, |v, k| v.get(k))
+    }
+
+    fn get_str<'a>(value: &'a Value, path: &[&str]) -> Option<&'a str> {
+        get(value, path).and_then(|v| v.as_str())
+    }
+
+    fn scalar_to_string(value: &Value) -> Option<String> {
+        match value {
+            Value::String(s) => Some(s.to_string()),
+            Value::Number(n) => Some(n.to_string()),
+            Value::Bool(b) => Some(b.to_string()),
+            _ => None,
+        }
+    }
+
+    fn mapping_entries(value: Option<&Value>) -> Vec<(String, String)> {
+        match value {
+            Some(Value::Mapping(m)) => m
+                .iter()
+                .filter_map(|(k, v)| Some((scalar_to_string(k)?, scalar_to_string(v)?)))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Expands `$(NAME)` with the variables defined before, leaving unknown names as they are.
+    fn expand(value: &str, defined: &[ValueDetail]) -> String {
+        let mut result = String::new();
+        let mut rest = value;
+        while let Some(i) = rest.find('$') {
+            result.push_str(&rest[..i]);
+            rest = &rest[i..];
+
+            if let Some(r) = rest.strip_prefix("$$") {
+                result.push('$');
+                rest = r;
+                continue;
+            }
+            let reference = rest
+                .strip_prefix("$(")
+                .and_then(|r| r.find(')').map(|end| &r[..end]));
+            let found =
+                reference.and_then(|n| defined.iter().rev().find(|e| e.key == n).map(|e| (n, e)));
+            match found {
+                Some((n, e)) => {
+                    result.push_str(&e.value);
+                    rest = &rest[n.len() + 3..];
+                }
+                None => {
+                    result.push('$');
+                    rest = &rest[1..];
+                }
+            }
+        }
+        result.push_str(rest);
+
+        result
+    }
+
+    /// Sources of the values referred to from containers.
+    struct Sources {
+        config_maps: Vec<(String, Vec<(String, String)>)>,
+        secrets: Vec<(String, Vec<(String, String)>)>,
+    }
+
+    impl Sources {
+        fn find(&self, secret: bool, name: &str) -> Option<&Vec<(String, String)>> {
+            let list = if secret {
+                &self.secrets
+            } else {
+                &self.config_maps
+            };
+            list.iter().find(|(n, _)| n == name).map(|(_, d)| d)
+        }
+
+        fn find_key(&self, secret: bool, name: &str, key: &str) -> Option<String> {
+            self.find(secret, name)?
+                .iter()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| v.to_string())
+        }
+    }
+
+    fn read_env_list(
+        list: &[Value],
+        sources: &Sources,
+        data: &mut Vec<ValueDetail>,
+        warnings: &mut Vec<String>,
+    ) {
+        let mut defined: Vec<ValueDetail> = Vec::new();
+
+        for item in list.iter() {
+            let key = match get_str(item, &["name"]) {
+                Some(s) => s,
+                None => continue,
+            };
+
+            let value = match (get(item, &["value"]), get(item, &["valueFrom"])) {
+                (Some(v), _) => scalar_to_string(v).map(|v| (expand(&v, &defined), false)),
+                (None, Some(from)) => {
+                    let reference = [("configMapKeyRef", false), ("secretKeyRef", true)]
+                        .iter()
+                        .find_map(|(k, secret)| from.get(k).map(|r| (r, *secret)));
+                    match reference {
+                        Some((r, secret)) => {
+                            let name = get_str(r, &["name"]).unwrap_or_default();
+                            let k = get_str(r, &["key"]).unwrap_or_default();
+                            match sources.find_key(secret, name, k) {
+                                Some(v) => Some((v, secret)),
+                                None => {
+                                    warnings.push(format!(
+                                        "{}: refers to {} of {}, which is not in the file, skipped",
+                                        key, k, name
+                                    ));
+                                    continue;
+                                }
+                            }
+                        }
+                        None => {
+                            warnings.push(format!(
+                                "{}: only values from a ConfigMap or a Secret can be converted, skipped",
+                                key
+                            ));
+                            continue;
+                        }
+                    }
+                }
+                (None, None) => Some(("".to_string(), false)),
+            };
+
+            if let Some((v, secret)) = value {
+                defined.push(entry(key, &v, secret));
+            }
+        }
+
+        data.append(&mut defined);
+    }
+
+    fn read_env_from(
+        list: &[Value],
+        sources: &Sources,
+        data: &mut Vec<ValueDetail>,
+        warnings: &mut Vec<String>,
+    ) {
+        for item in list.iter() {
+            let prefix = get_str(item, &["prefix"]).unwrap_or_default();
+            let reference = [("configMapRef", false), ("secretRef", true)]
+                .iter()
+                .find_map(|(k, secret)| item.get(k).map(|r| (r, *secret)));
+            let (name, secret) = match reference {
+                Some((r, secret)) => (get_str(r, &["name"]).unwrap_or_default(), secret),
+                None => continue,
+            };
+
+            match sources.find(secret, name) {
+                Some(d) => {
+                    for (k, v) in d.iter() {
+                        data.push(entry(&format!("{}{}", prefix, k), v, secret));
+                    }
+                }
+                None => warnings.push(format!("envFrom: {} is not in the file, skipped", name)),
+            }
+        }
+    }
+
+    /// Converts ConfigMaps, Secrets and the `env:` of containers in Kubernetes manifests
+    /// into a config. Values of Secrets are flagged secret.
+    /// Returns the config and the warnings about what could not be converted.
+    pub fn parse_manifests(content: &str) -> Result<(Config, Vec<String>), String> {
+        let mut documents: Vec<Value> = Vec::new();
+        for d in serde_yaml::Deserializer::from_str(content) {
+            match Value::deserialize(d) {
+                Ok(Value::Null) => {}
+                Ok(v) => documents.push(v),
+                Err(e) => return Err(e.to_string()),
+            }
+        }
+        // a List holds the manifests as its items
+        let documents = documents
+            .into_iter()
+            .flat_map(|d| match (get_str(&d, &["kind"]), d.get("items")) {
+                (Some("List"), Some(Value::Sequence(items))) => items.to_vec(),
+                _ => vec![d],
+            })
+            .collect::<Vec<Value>>();
+
+        let mut warnings: Vec<String> = Vec::new();
+        let mut sources = Sources {
+            config_maps: Vec::new(),
+            secrets: Vec::new(),
+        };
+        let mut containers: Vec<&Value> = Vec::new();
+        for d in documents.iter() {
+            let name = get_str(d, &["metadata", "name"])
+                .unwrap_or_default()
+                .to_string();
+            match get_str(d, &["kind"]) {
+                Some("ConfigMap") => {
+                    if get(d, &["binaryData"]).is_some() {
+                        warnings.push(format!("{}: binaryData cannot be converted, skipped", name));
+                    }
+                    sources
+                        .config_maps
+                        .push((name, mapping_entries(get(d, &["data"]))));
+                }
+                Some("Secret") => {
+                    let mut data: Vec<(String, String)> = Vec::new();
+                    for (k, v) in mapping_entries(get(d, &["data"])) {
+                        let decoded = STANDARD.decode(v.trim()).ok();
+                        match decoded.and_then(|b| String::from_utf8(b).ok()) {
+                            Some(s) => data.push((k, s)),
+                            None => warnings.push(format!(
+                                "{}: not base64 encoded text in Secret {}, skipped",
+                                k, name
+                            )),
+                        }
+                    }
+                    data.extend(mapping_entries(get(d, &["stringData"])));
+                    sources.secrets.push((name, data));
+                }
+                _ => {
+                    // `env:` written by config_to_env_list, or a workload holding containers
+                    if d.get("env").is_some() {
+                        containers.push(d);
+                    }
+                    for path in [
+                        &["spec"][..],
+                        &["spec", "template", "spec"][..],
+                        &["spec", "jobTemplate", "spec", "template", "spec"][..],
+                    ] {
+                        if let Some(Value::Sequence(list)) =
+                            get(d, &[path, &["containers"]].concat())
+                        {
+                            containers.extend(list.iter());
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut data: Vec<ValueDetail> = Vec::new();
+        if containers.is_empty() {
+            for (secret, list) in [(false, &sources.config_maps), (true, &sources.secrets)] {
+                for (_, d) in list.iter() {
+                    for (k, v) in d.iter() {
+                        data.push(entry(k, v, secret));
+                    }
+                }
+            }
+        }
+        for c in containers.iter() {
+            if let Some(Value::Sequence(list)) = c.get("envFrom") {
+                read_env_from(list, &sources, &mut data, &mut warnings);
+            }
+            if let Some(Value::Sequence(list)) = c.get("env") {
+                read_env_list(list, &sources, &mut data, &mut warnings);
+            }
+        }
+
+        // the last one wins as in a container
+        let mut result: Vec<ValueDetail> = Vec::new();
+        for e in data.into_iter() {
+            result.retain(|r| r.key != e.key);
+            result.push(e);
+        }
+
+        Ok((
+            Config {
+                version: 1,
+                data: result,
+                metadata: None,
+            },
+            warnings,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::json::config::Config;
+    use crate::kubernetes::kubernetes::{config_to_env_list, config_to_manifests, parse_manifests};
+
+    fn entries(config: &Config) -> Vec<(&str, &str, bool)> {
+        config
+            .data
+            .iter()
+            .map(|e| (e.key.as_str(), e.value.as_str(), e.secret))
+            .collect()
+    }
+
+    fn config() -> Config {
+        let json = r#"{"version":1,"data":[
+            {"key":"PORT","value":"8080","overwrite":true},
+            {"key":"TOKEN","value":"s3cr3t","overwrite":true,"secret":true},
+            {"key":"PATH","value":"/opt/bin","delimiter":":","insert":0},
+            {"key":"PRICE","value":"$(5)","overwrite":true}
+        ]}"#;
+        serde_json::from_str::<Config>(json).unwrap()
+    }
+
+    #[test]
+    fn test_manifests() {
+        let (content, warnings) = config_to_manifests(&config(), "app").unwrap();
+        assert_eq!(
+            vec!["PATH: extending a list cannot be expressed on Kubernetes, skipped"],
+            warnings
+        );
+        assert!(content.contains("kind: ConfigMap\n"));
+        assert!(content.contains("kind: Secret\n"));
+        assert!(content.contains("  TOKEN: czNjcjN0\n"));
+
+        let (read_back, warnings) = parse_manifests(&content).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(
+            vec![
+                ("PORT", "8080", false),
+                ("PRICE", "$(5)", false),
+                ("TOKEN", "s3cr3t", true),
+            ],
+            entries(&read_back)
+        );
+    }
+
+    #[test]
+    fn test_env_list() {
+        let (content, _) = config_to_env_list(&config(), "app").unwrap();
+        assert!(content.contains("secretKeyRef:\n"));
+        assert!(content.contains("value: $$(5)\n"));
+
+        let (read_back, warnings) = parse_manifests(&content).unwrap();
+        assert_eq!(
+            vec!["TOKEN: refers to TOKEN of app, which is not in the file, skipped"],
+            warnings
+        );
+        assert_eq!(
+            vec![("PORT", "8080", false), ("PRICE", "$(5)", false)],
+            entries(&read_back)
+        );
+    }
+
+    #[test]
+    fn test_deployment() {
+        let content = r#"
+apiVersion: v1
+kind: Secret
+metadata:
+  name: creds
+stringData:
+  PASSWORD: hunter2
+---
+apiVersion: apps/v1
+kind: Deployment
+metadata:
+  name: web
+spec:
+  template:
+    spec:
+      containers:
+        - name: web
+          env:
+            - name: HOST
+              value: example.com
+            - name: URL
+              value: https://$(HOST)/$(UNKNOWN)
+            - name: PASSWORD
+              valueFrom:
+                secretKeyRef:
+                  name: creds
+                  key: PASSWORD
+            - name: POD
+              valueFrom:
+                fieldRef:
+                  fieldPath: metadata.name
+"#;
+        let (config, warnings) = parse_manifests(content).unwrap();
+        assert_eq!(
+            vec![
+                ("HOST", "example.com", false),
+                ("URL", "https://example.com/$(UNKNOWN)", false),
+                ("PASSWORD", "hunter2", true),
+            ],
+            entries(&config)
+        );
+        assert_eq!(1, warnings.len());
+    }
+}
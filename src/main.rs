@@ -1,15 +1,46 @@
+#[cfg(target_os = "linux")]
+mod activate;
 mod args;
+#[cfg(target_os = "linux")]
+mod capture;
+mod diff;
+mod docker;
+mod dotenv;
 mod envvar;
+mod format;
+#[cfg(target_os = "linux")]
+mod hook;
 mod json;
+mod kubernetes;
+mod merge;
+mod portable;
+#[cfg(target_os = "linux")]
+mod probe;
+#[cfg(target_os = "linux")]
+mod rcparse;
+mod run;
 mod settings;
+mod snapshot;
+mod source;
+mod state;
 mod utils;
 
 #[cfg(target_os = "linux")]
 mod shellrc;
-
-use crate::{envvar::environment_variable::EnvironmentVariable, json::config};
 #[cfg(target_os = "linux")]
-use shellrc::shellrc::ShellRunCommandFileData;
+mod startup;
+#[cfg(target_os = "linux")]
+mod subshell;
+#[cfg(target_os = "linux")]
+mod system;
+#[cfg(target_os = "linux")]
+mod systemd;
+#[cfg(target_os = "linux")]
+mod verify;
+#[cfg(target_os = "linux")]
+mod watch;
+
+use crate::envvar::environment_variable::EnvironmentVariable;
 use std::{
     io::{self, Write},
     path::Path,
@@ -39,8 +70,135 @@ fn main() -> io::Result<()> {
         return Ok(());
     }
 
+    if settings.command == Some("exec") {
+        if let Err(e) = run::run::exec(&settings) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    if settings.command == Some("snapshot") {
+        if let Err(e) = snapshot::snapshot::snapshot(&settings) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    if settings.command == Some("merge") {
+        if let Err(e) = merge::merge::merge_files(&settings) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    if settings.command == Some("diff") {
+        if let Err(e) = diff::diff::diff(&settings) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        let result = match settings.command {
+            Some("install") => Some(startup::startup::install(&settings)),
+            Some("uninstall") => Some(startup::startup::uninstall(&settings)),
+            Some("activate") => Some(activate::activate::activate(&settings)),
+            Some("deactivate") => Some(activate::activate::deactivate(&settings)),
+            Some("shell") => Some(subshell::subshell::shell(&settings)),
+            Some("hook") => Some(hook::hook::hook(&settings)),
+            Some("hook-env") => Some(hook::hook::hook_env(&settings)),
+            Some("allow") => Some(hook::hook::allow(&settings)),
+            Some("deny") => Some(hook::hook::deny(&settings)),
+            Some("from-rc") => Some(rcparse::rcparse::from_rc(&settings)),
+            Some("capture") => Some(capture::capture::capture(&settings)),
+            Some("verify-rc") => Some(verify::verify::verify_rc(&settings)),
+            Some("watch") => Some(watch::watch::watch(&settings)),
+            _ => None,
+        };
+
+        match result {
+            Some(Ok(_)) => return Ok(()),
+            Some(Err(e)) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+            None => {}
+        }
+    }
+
+    // the environment of another process or a container, instead of the current one
+    let source = match source::source::open(&settings) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    if source.is_some() && settings.import.is_some() {
+        eprintln!("--pid and --oci are read-only and cannot be used with --import");
+        std::process::exit(1);
+    }
+
+    // how lists and paths are converted between Windows and POSIX
+    let translation = match portable::portable::Translation::from_settings(&settings) {
+        Ok(t) => t,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
     if settings.export.is_some() {
-        match config::export_envvar(Path::new(settings.export.unwrap())) {
+        let output = Path::new(settings.export.unwrap());
+        let result = match settings.import {
+            // convert the file given with --import instead of the environment
+            Some(input) => format::format::convert(
+                Path::new(input),
+                settings.input_format,
+                output,
+                settings.format,
+                &translation,
+            ),
+            None => match &source {
+                Some(s) => format::format::export_envvar(
+                    s,
+                    output,
+                    settings.format,
+                    &settings.filter,
+                    &translation,
+                ),
+                None => format::format::export_envvar(
+                    &envvar::environment_variable::env::Environment::new(),
+                    output,
+                    settings.format,
+                    &settings.filter,
+                    &translation,
+                ),
+            },
+        };
+        match result {
+            Ok(_) => return Ok(()),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    if settings.sync && (settings.system || settings.shell_rc.target.is_some_and(|t| t != "rc")) {
+        eprintln!("--sync is supported only for the rc file");
+        std::process::exit(1);
+    }
+
+    #[cfg(target_os = "linux")]
+    if settings.import.is_some() && settings.system {
+        match system::system::import(&settings) {
             Ok(_) => return Ok(()),
             Err(e) => {
                 eprintln!("{}", e);
@@ -50,10 +208,60 @@ fn main() -> io::Result<()> {
     }
 
     if settings.import.is_some() {
+        // environment.d and EnvironmentFile are written instead of the rc file
+        #[cfg(target_os = "linux")]
+        match systemd::systemd::resolve_target(settings.shell_rc.target) {
+            Ok(Some(target)) => match systemd::systemd::import(&settings, target) {
+                Ok(_) => return Ok(()),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            },
+            Ok(None) => {}
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+
         let mut envvar = envvar::environment_variable::env::Environment::new();
-        match config::import_envvar(
+
+        #[cfg(target_os = "linux")]
+        let shell = match shellrc::detect::resolve_shell(settings.shell_rc.shell) {
+            Ok((shell, origin)) => {
+                if settings.verbose > 0 {
+                    println!("shell: {} ({})", shell, origin);
+                }
+                shell
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        };
+
+        #[cfg(target_os = "linux")]
+        envvar.init_shell(&shell.to_string());
+
+        #[cfg(target_os = "linux")]
+        let output_path = match settings.shell_rc.output_rc {
+            Some(ref s) => s.to_string(),
+            None => shellrc::shellrc::default_rc_path(shell),
+        };
+        // the variables set by envvar are tracked for each rc file
+        #[cfg(target_os = "linux")]
+        let target = output_path.as_str();
+        #[cfg(target_os = "windows")]
+        let target = "registry";
+
+        let managed = match state::state::import_envvar(
             Path::new(settings.import.unwrap()),
+            settings.input_format,
+            target,
             settings.dry_run,
+            settings.sync,
+            &translation,
             &mut envvar,
         ) {
             Ok(e) => e,
@@ -65,25 +273,42 @@ fn main() -> io::Result<()> {
 
         #[cfg(target_os = "linux")]
         {
-            let output_path = match settings.shell_rc.output_rc {
-                Some(s) => s,
-                None => format!(".envvar_{}rc", settings.shell_rc.shell.unwrap()),
-            };
+            if settings.verbose > 0 {
+                println!("rc: {}", output_path);
+            }
 
-            envvar.init_shell(&settings.shell_rc.shell.unwrap().to_string());
-            match envvar.write_rc(&Path::new(&output_path)) {
-                Ok(_) => return Ok(()),
-                Err(e) => {
-                    eprintln!("{}", e);
-                    std::process::exit(1);
-                }
+            if let Err(e) = envvar.write_rc(&Path::new(&output_path)) {
+                eprintln!("{}", e);
+                std::process::exit(1);
             }
         }
+
+        // recorded only once the target is written
+        if let Some(m) = managed
+            && let Err(e) = state::state::record(m)
+        {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        #[cfg(target_os = "linux")]
+        return Ok(());
     }
 
     let env = envvar::environment_variable::env::Environment::new();
 
-    match env.list() {
+    #[cfg(target_os = "linux")]
+    let list = match &source {
+        Some(s) => s.list(),
+        None if settings.system => system::system::list(&settings),
+        None => env.list(),
+    };
+    #[cfg(not(target_os = "linux"))]
+    let list = match &source {
+        Some(s) => s.list(),
+        None => env.list(),
+    };
+
+    match list {
         Ok(l) => {
             let choice = if settings.no_color {
                 ColorChoice::Never
@@ -0,0 +1,440 @@
+pub mod merge {
+    use std::io::{BufRead, Write};
+    use std::path::Path;
+
+    use crate::format::format::{self, Format};
+    use crate::json::config::{Config, ValueDetail};
+    use crate::settings::settings::Settings;
+
+    /// The entries of one key after the merge.
+    #[derive(Debug, PartialEq)]
+    pub enum Merged {
+        Resolved(Vec<ValueDetail>),
+        /// Both sides changed the key differently, an empty side meaning deleted.
+        Conflict {
+            key: String,
+            base: Vec<ValueDetail>,
+            ours: Vec<ValueDetail>,
+            theirs: Vec<ValueDetail>,
+        },
+    }
+
+    fn entries_of(config: &Config, key: &str) -> Vec<ValueDetail> {
+        config
+            .data
+            .iter()
+            .filter(|e| e.key == key)
+            .cloned()
+            .collect()
+    }
+
+    /// Merges the elements of a list: what `theirs` added is placed after the element it
+    /// follows, and what it removed is removed, keeping the order of `ours`.
+    pub fn merge_elements(base: &[&str], ours: &[&str], theirs: &[&str]) -> Vec<String> {
+        let mut result = ours
+            .iter()
+            .filter(|e| !base.contains(e) || theirs.contains(e))
+            .map(|e| e.to_string())
+            .collect::<Vec<String>>();
+
+        for (i, e) in theirs.iter().enumerate() {
+            if base.contains(e) || result.iter().any(|r| r == e) {
+                continue;
+            }
+            let position = theirs[..i]
+                .iter()
+                .rev()
+                .find_map(|p| result.iter().position(|r| r == p))
+                .map(|p| p + 1)
+                .unwrap_or(0);
+            result.insert(position, e.to_string());
+        }
+
+        result
+    }
+
+    fn split_list<'a>(value: &'a str, delimiter: &str) -> Vec<&'a str> {
+        match value.is_empty() {
+            true => Vec::new(),
+            false => value.split(delimiter).collect(),
+        }
+    }
+
+    /// Merges both sides of a list entry element by element, if they differ only in the value.
+    fn merge_list(
+        base: &[ValueDetail],
+        ours: &[ValueDetail],
+        theirs: &[ValueDetail],
+    ) -> Option<ValueDetail> {
+        let (o, t) = match (ours, theirs) {
+            ([o], [t]) => (o, t),
+            _ => return None,
+        };
+        let is_same_kind = |a: &ValueDetail, b: &ValueDetail| {
+            (&a.delimiter, a.overwrite, a.insert, a.secret)
+                == (&b.delimiter, b.overwrite, b.insert, b.secret)
+        };
+        if o.delimiter.is_empty() || !is_same_kind(o, t) {
+            return None;
+        }
+        let base_value = match base {
+            [] => "",
+            [b] if b.delimiter == o.delimiter => b.value.as_str(),
+            _ => return None,
+        };
+
+        let merged = merge_elements(
+            &split_list(base_value, &o.delimiter),
+            &split_list(&o.value, &o.delimiter),
+            &split_list(&t.value, &t.delimiter),
+        );
+
+        let mut result = o.clone();
+        result.value = merged.join(&o.delimiter);
+        Some(result)
+    }
+
+    /// Merges `ours` and `theirs`, both changed from `base`, entry by entry. The keys are
+    /// in the order of `ours`, followed by the ones only `theirs` has.
+    pub fn merge(base: &Config, ours: &Config, theirs: &Config) -> Vec<Merged> {
+        let mut keys: Vec<&String> = Vec::new();
+        for e in ours.data.iter().chain(theirs.data.iter()) {
+            if !keys.contains(&&e.key) {
+                keys.push(&e.key);
+            }
+        }
+
+        let mut result: Vec<Merged> = Vec::new();
+        for key in keys {
+            let b = entries_of(base, key);
+            let o = entries_of(ours, key);
+            let t = entries_of(theirs, key);
+
+            let merged = if o == t || t == b {
+                Merged::Resolved(o)
+            } else if o == b {
+                Merged::Resolved(t)
+            } else if let Some(e) = merge_list(&b, &o, &t) {
+                Merged::Resolved(vec![e])
+            } else {
+                Merged::Conflict {
+                    key: key.to_string(),
+                    base: b,
+                    ours: o,
+                    theirs: t,
+                }
+            };
+            result.push(merged);
+        }
+
+        result
+    }
+
+    fn describe(entries: &[ValueDetail]) -> String {
+        if entries.is_empty() {
+            return "(deleted)".to_string();
+        }
+
+        entries
+            .iter()
+            .map(|e| match (e.overwrite, e.delimiter.is_empty(), e.insert) {
+                (false, false, i) if i < 0 => format!("{} (append, {:?})", e.value, e.delimiter),
+                (false, false, i) => format!("{} (insert at {}, {:?})", e.value, i, e.delimiter),
+                (true, _, _) => format!("{} (overwrite)", e.value),
+                _ => e.value.to_string(),
+            })
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+
+    /// Asks which side to take for each conflict, reading the answers from `input`.
+    pub fn resolve<R: BufRead, W: Write>(
+        merged: Vec<Merged>,
+        input: &mut R,
+        output: &mut W,
+    ) -> Result<Vec<Merged>, String> {
+        let mut result: Vec<Merged> = Vec::new();
+        for m in merged {
+            let (key, base, ours, theirs) = match m {
+                Merged::Conflict {
+                    key,
+                    base,
+                    ours,
+                    theirs,
+                } => (key, base, ours, theirs),
+                resolved => {
+                    result.push(resolved);
+                    continue;
+                }
+            };
+
+            let prompt = format!(
+                "conflict: {}\n  base:   {}\n  ours:   {}\n  theirs: {}\ntake [o]urs, [t]heirs or [b]ase? ",
+                key,
+                describe(&base),
+                describe(&ours),
+                describe(&theirs)
+            );
+            let chosen = loop {
+                if let Err(e) = write!(output, "{}", prompt).and_then(|_| output.flush()) {
+                    return Err(e.to_string());
+                }
+                let mut answer = String::new();
+                match input.read_line(&mut answer) {
+                    Ok(0) => return Err(format!("{}: no answer", key)),
+                    Ok(_) => {}
+                    Err(e) => return Err(e.to_string()),
+                }
+                match answer.trim() {
+                    "o" | "ours" => break ours,
+                    "t" | "theirs" => break theirs,
+                    "b" | "base" => break base,
+                    _ => {}
+                }
+            };
+            result.push(Merged::Resolved(chosen));
+        }
+
+        Ok(result)
+    }
+
+    fn entry_to_string(entry: &ValueDetail, comma: bool) -> Result<String, String> {
+        let s = match serde_json::to_string_pretty(entry) {
+            Ok(s) => s,
+            Err(e) => return Err(e.to_string()),
+        };
+        let mut result = s
+            .lines()
+            .map(|l| format!("    {}", l))
+            .collect::<Vec<String>>()
+            .join("\n");
+        if comma {
+            result.push(',');
+        }
+        result.push('\n');
+
+        Ok(result)
+    }
+
+    /// Appends `entries`. A comma goes before an entry when one surely precedes it, and after
+    /// the last one with `trailing`, so that either side of a conflict leaves a valid config.
+    fn push_entries(
+        result: &mut String,
+        entries: &[ValueDetail],
+        preceded: bool,
+        trailing: bool,
+    ) -> Result<(), String> {
+        for (i, e) in entries.iter().enumerate() {
+            if preceded || i > 0 {
+                // after a marker line, the comma takes a line of its own
+                if result.ends_with("}\n") {
+                    result.pop();
+                    result.push_str(",\n");
+                } else {
+                    result.push_str("    ,\n");
+                }
+            }
+            result.push_str(&entry_to_string(e, trailing && i + 1 == entries.len())?);
+        }
+
+        Ok(())
+    }
+
+    /// Writes the result as a JSON config, with each conflict between markers
+    /// labeled `ours_label` and `theirs_label` as git does.
+    pub fn to_string_with_markers(
+        merged: &[Merged],
+        ours_label: &str,
+        theirs_label: &str,
+    ) -> Result<String, String> {
+        // an entry that stays whichever side is taken
+        let is_certain = |m: &Merged| match m {
+            Merged::Resolved(e) => !e.is_empty(),
+            Merged::Conflict { ours, theirs, .. } => !ours.is_empty() && !theirs.is_empty(),
+        };
+
+        let mut result = String::from("{\n  \"version\": 1,\n  \"data\": [\n");
+        for (i, m) in merged.iter().enumerate() {
+            let preceded = merged[..i].iter().any(is_certain);
+            match m {
+                Merged::Resolved(entries) => push_entries(&mut result, entries, preceded, false)?,
+                Merged::Conflict { ours, theirs, .. } => {
+                    // without an entry before, the one after is joined by the side taken
+                    let trailing = !preceded && merged[i + 1..].iter().any(is_certain);
+                    result.push_str(&format!("<<<<<<< {}\n", ours_label));
+                    push_entries(&mut result, ours, preceded, trailing)?;
+                    result.push_str("=======\n");
+                    push_entries(&mut result, theirs, preceded, trailing)?;
+                    result.push_str(&format!(">>>>>>> {}\n", theirs_label));
+                }
+            }
+        }
+        result.push_str("  ]\n}");
+
+        Ok(result)
+    }
+
+    /// Runs `merge base ours theirs`, writing the result to --export or stdout.
+    /// Conflicts are asked with --interactive, or left between markers.
+    pub fn merge_files(settings: &Settings) -> Result<(), String> {
+        let (base, ours, theirs) = match settings.operands[..] {
+            [b, o, t] => (b, o, t),
+            _ => return Err("merge needs three files: base ours theirs".to_string()),
+        };
+        let read = |path: &str| format::read_config(Path::new(path), settings.input_format);
+        let mut merged = merge(&read(base)?, &read(ours)?, &read(theirs)?);
+
+        if settings.interactive {
+            let stdin = std::io::stdin();
+            merged = resolve(merged, &mut stdin.lock(), &mut std::io::stderr())?;
+        }
+
+        let conflicts = merged
+            .iter()
+            .filter(|m| matches!(m, Merged::Conflict { .. }))
+            .count();
+        if conflicts == 0 {
+            let data = merged
+                .into_iter()
+                .flat_map(|m| match m {
+                    Merged::Resolved(entries) => entries,
+                    Merged::Conflict { .. } => Vec::new(),
+                })
+                .collect::<Vec<ValueDetail>>();
+            let config = Config {
+                version: 1,
+                data,
+                metadata: None,
+            };
+            return match settings.export {
+                Some(s) => format::write_config(&config, Path::new(s), settings.format),
+                None => format::print_config(&config, settings.format),
+            };
+        }
+
+        // markers are written as they are, so the format must be json
+        let path = Path::new(settings.export.unwrap_or_default());
+        if format::resolve(settings.format, path)? != Format::Json {
+            return Err(format!(
+                "{} conflicts can be marked only in json, resolve them with --interactive",
+                conflicts
+            ));
+        }
+        let content = to_string_with_markers(&merged, ours, theirs)?;
+        match settings.export {
+            Some(s) => {
+                if let Err(e) = std::fs::write(s, format!("{}\n", content)) {
+                    return Err(format!("{}: {}", e, s));
+                }
+            }
+            None => println!("{}", content),
+        }
+
+        Err(format!("{} conflicts, marked in the output", conflicts))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::json::config::Config;
+    use crate::merge::merge::{Merged, merge, merge_elements, resolve, to_string_with_markers};
+
+    fn config(json: &str) -> Config {
+        serde_json::from_str(json).unwrap()
+    }
+
+    /// Resolves every conflict of `s` to one side, as an editor would.
+    fn take_side(s: &str, ours: bool) -> Config {
+        // the side being read, if inside a conflict
+        let mut side: Option<bool> = None;
+        let mut lines: Vec<&str> = Vec::new();
+        for l in s.lines() {
+            match l {
+                l if l.starts_with("<<<<<<<") => side = Some(true),
+                "=======" => side = Some(false),
+                l if l.starts_with(">>>>>>>") => side = None,
+                l if side.is_none_or(|s| s == ours) => lines.push(l),
+                _ => {}
+            }
+        }
+        config(&lines.join("\n"))
+    }
+
+    #[test]
+    fn test_merge_elements() {
+        assert_eq!(
+            vec!["/ours", "/usr/bin", "/theirs", "/bin"],
+            merge_elements(
+                &["/usr/bin", "/old", "/bin"],
+                &["/ours", "/usr/bin", "/old", "/bin"],
+                &["/usr/bin", "/theirs", "/bin"],
+            )
+        );
+    }
+
+    #[test]
+    fn test_merge() {
+        let base = config(
+            r#"{"version":1,"data":[
+            {"key":"PATH","value":"/usr/bin:/bin","delimiter":":"},
+            {"key":"KEEP","value":"1"},
+            {"key":"EDITOR","value":"vi"},
+            {"key":"GONE","value":"x"}]}"#,
+        );
+        let ours = config(
+            r#"{"version":1,"data":[
+            {"key":"PATH","value":"/opt/a:/usr/bin:/bin","delimiter":":"},
+            {"key":"KEEP","value":"1"},
+            {"key":"EDITOR","value":"vim"}]}"#,
+        );
+        let theirs = config(
+            r#"{"version":1,"data":[
+            {"key":"PATH","value":"/usr/bin:/bin:/opt/b","delimiter":":"},
+            {"key":"KEEP","value":"2"},
+            {"key":"EDITOR","value":"nano"},
+            {"key":"GONE","value":"x"},
+            {"key":"NEW","value":"y"}]}"#,
+        );
+
+        let merged = merge(&base, &ours, &theirs);
+        let resolved = |m: &Merged| match m {
+            Merged::Resolved(e) => e.iter().map(|e| e.value.to_string()).collect(),
+            Merged::Conflict { .. } => vec!["conflict".to_string()],
+        };
+        assert_eq!(
+            vec![
+                vec!["/opt/a:/usr/bin:/bin:/opt/b".to_string()],
+                vec!["2".to_string()],
+                vec!["conflict".to_string()],
+                vec![],
+                vec!["y".to_string()],
+            ],
+            merged.iter().map(resolved).collect::<Vec<Vec<String>>>()
+        );
+
+        let s = to_string_with_markers(&merged, "ours.json", "theirs.json").unwrap();
+        assert!(s.contains("<<<<<<< ours.json\n    ,\n    {\n      \"key\": \"EDITOR\""));
+        assert_eq!(4, take_side(&s, true).data.len());
+        assert_eq!(4, take_side(&s, false).data.len());
+
+        let mut input = "x\nt\n".as_bytes();
+        let mut output: Vec<u8> = Vec::new();
+        let result = resolve(merged, &mut input, &mut output).unwrap();
+        assert_eq!(vec!["nano".to_string()], resolved(&result[2]));
+        assert!(String::from_utf8(output).unwrap().contains("theirs: nano"));
+    }
+
+    #[test]
+    fn test_markers_with_deleted_side() {
+        let base =
+            config(r#"{"version":1,"data":[{"key":"A","value":"1"},{"key":"B","value":"1"}]}"#);
+        let ours = config(r#"{"version":1,"data":[{"key":"A","value":"2"}]}"#);
+        let theirs =
+            config(r#"{"version":1,"data":[{"key":"A","value":"3"},{"key":"B","value":"2"}]}"#);
+
+        // both keys conflict, the last one deleted on our side
+        let s = to_string_with_markers(&merge(&base, &ours, &theirs), "o", "t").unwrap();
+        assert_eq!(1, take_side(&s, true).data.len());
+        assert_eq!(2, take_side(&s, false).data.len());
+    }
+}
@@ -0,0 +1,501 @@
+/// Differences between Windows and POSIX in lists and paths, kept as string logic so that
+/// either side can be handled on any platform.
+pub mod portable {
+    use crate::json::config::{Config, ValueDetail};
+    use crate::settings::settings::Settings;
+
+    /// Where Windows drives are mounted on the POSIX side, as WSL does by default.
+    pub const DEFAULT_MOUNT_ROOT: &str = "/mnt";
+
+    /// Styles accepted by `--platform`.
+    pub const PLATFORMS: [&str; 2] = ["posix", "windows"];
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum Style {
+        Posix,
+        Windows,
+    }
+
+    impl Style {
+        pub fn delimiter(self) -> &'static str {
+            match self {
+                Style::Posix => ":",
+                Style::Windows => ";",
+            }
+        }
+
+        /// Returns the name of the search path, as `env::PATH` of the platform spells it.
+        pub fn path_key(self) -> &'static str {
+            match self {
+                Style::Posix => "PATH",
+                Style::Windows => "Path",
+            }
+        }
+
+        pub fn name(self) -> &'static str {
+            match self {
+                Style::Posix => "posix",
+                Style::Windows => "windows",
+            }
+        }
+    }
+
+    /// Returns the style named `name`, as given to `--platform`.
+    pub fn parse_style(name: &str) -> Result<Style, String> {
+        match name {
+            "posix" | "linux" | "wsl" => Ok(Style::Posix),
+            "windows" => Ok(Style::Windows),
+            _ => Err(format!(
+                "unknown platform: {} (supported: {})",
+                name,
+                PLATFORMS.join(", ")
+            )),
+        }
+    }
+
+    /// Returns the style of the platform this runs on.
+    pub fn current() -> Style {
+        match cfg!(target_os = "windows") {
+            true => Style::Windows,
+            false => Style::Posix,
+        }
+    }
+
+    /// Returns the style of `platform` as recorded in the metadata, e.g., `linux`.
+    pub fn platform_style(platform: &str) -> Style {
+        match platform {
+            "windows" => Style::Windows,
+            _ => Style::Posix,
+        }
+    }
+
+    /// Returns true for `C:\dir`, `C:/dir`, `C:` and `\\server\share`.
+    pub fn is_windows_path(s: &str) -> bool {
+        let b = s.as_bytes();
+        let has_drive = b.len() >= 2
+            && b[0].is_ascii_alphabetic()
+            && b[1] == b':'
+            && (b.len() == 2 || b[2] == b'\\' || b[2] == b'/');
+        has_drive || s.starts_with("\\\\")
+    }
+
+    /// Converts `C:\Tools\bin` into `<mount_root>/c/Tools/bin`. UNC paths have no equivalent.
+    pub fn to_posix_path(s: &str, mount_root: &str) -> Option<String> {
+        if !is_windows_path(s) || s.starts_with("\\\\") {
+            return None;
+        }
+
+        let drive = s[..1].to_ascii_lowercase();
+        let rest = s[2..].replace('\\', "/");
+        let rest = rest.trim_matches('/');
+        let root = mount_root.trim_end_matches('/');
+        match rest.is_empty() {
+            true => Some(format!("{}/{}", root, drive)),
+            false => Some(format!("{}/{}/{}", root, drive, rest)),
+        }
+    }
+
+    /// Converts `<mount_root>/c/Tools/bin` into `C:\Tools\bin`. Other paths have no equivalent.
+    pub fn to_windows_path(s: &str, mount_root: &str) -> Option<String> {
+        let root = mount_root.trim_end_matches('/');
+        let rest = s.strip_prefix(root)?.strip_prefix('/')?;
+        let (drive, rest) = match rest.split_once('/') {
+            Some((d, r)) => (d, r),
+            None => (rest, ""),
+        };
+        if drive.len() != 1 || !drive.as_bytes()[0].is_ascii_alphabetic() {
+            return None;
+        }
+
+        Some(format!(
+            "{}:\\{}",
+            drive.to_ascii_uppercase(),
+            rest.trim_end_matches('/').replace('/', "\\")
+        ))
+    }
+
+    /// Returns `root` of the `[automount]` section of a `wsl.conf`, if set.
+    pub fn parse_wsl_mount_root(conf: &str) -> Option<String> {
+        let mut section = "";
+        for line in conf.lines() {
+            let line = line.trim();
+            if line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = name.trim();
+                continue;
+            }
+            if !section.eq_ignore_ascii_case("automount") {
+                continue;
+            }
+            if let Some((k, v)) = line.split_once('=')
+                && k.trim() == "root"
+            {
+                let v = v.trim().trim_matches('"');
+                if !v.is_empty() {
+                    return Some(v.to_string());
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Returns `given`, or the mount root of WSL on this machine, or [`DEFAULT_MOUNT_ROOT`].
+    pub fn mount_root(given: Option<&str>) -> String {
+        if let Some(s) = given {
+            return s.to_string();
+        }
+
+        #[cfg(target_os = "linux")]
+        if let Ok(s) = std::fs::read_to_string("/etc/wsl.conf")
+            && let Some(root) = parse_wsl_mount_root(&s)
+        {
+            return root;
+        }
+
+        DEFAULT_MOUNT_ROOT.to_string()
+    }
+
+    /// How lists and paths are converted on `--import` and `--export`.
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct Translation {
+        /// Converts a config of another platform on import instead of warning
+        pub enabled: bool,
+        /// Style to export in, as is if none
+        pub platform: Option<Style>,
+        pub mount_root: String,
+    }
+
+    impl Translation {
+        pub fn from_settings(settings: &Settings) -> Result<Translation, String> {
+            let platform = match settings.platform {
+                Some(s) => Some(parse_style(s)?),
+                None => None,
+            };
+
+            Ok(Translation {
+                enabled: settings.translate,
+                platform,
+                mount_root: mount_root(settings.mount_root),
+            })
+        }
+    }
+
+    /// Lists the keys of `config` that differ only in case, e.g., `Path` and `PATH`, which are
+    /// one variable on Windows.
+    pub fn collisions(config: &Config) -> Vec<String> {
+        let mut warnings: Vec<String> = Vec::new();
+        let mut seen: Vec<&str> = Vec::new();
+        for e in config.data.iter() {
+            if seen.contains(&e.key.as_str()) {
+                continue;
+            }
+            if let Some(other) = seen.iter().find(|k| k.eq_ignore_ascii_case(&e.key)) {
+                warnings.push(format!(
+                    "{} and {} differ only in case, they are one variable on windows",
+                    other, e.key
+                ));
+            }
+            seen.push(&e.key);
+        }
+
+        warnings
+    }
+
+    /// Guesses the style of a config without metadata from its lists and paths.
+    pub fn guess(config: &Config) -> Option<Style> {
+        for e in config.data.iter() {
+            if e.delimiter == ";" || e.value.split(';').any(is_windows_path) {
+                return Some(Style::Windows);
+            }
+        }
+        for e in config.data.iter() {
+            if e.delimiter == ":" && e.value.split(':').any(|v| v.starts_with('/')) {
+                return Some(Style::Posix);
+            }
+        }
+
+        None
+    }
+
+    /// Returns the style the config was exported in, from the metadata or guessed.
+    pub fn source_style(config: &Config) -> Option<Style> {
+        match &config.metadata {
+            Some(m) => Some(platform_style(&m.platform)),
+            None => guess(config),
+        }
+    }
+
+    fn is_foreign_path(s: &str, from: Style) -> bool {
+        match from {
+            Style::Windows => is_windows_path(s),
+            Style::Posix => s.starts_with('/'),
+        }
+    }
+
+    fn translate_path(
+        key: &str,
+        s: &str,
+        to: Style,
+        mount_root: &str,
+        warnings: &mut Vec<String>,
+    ) -> String {
+        let result = match to {
+            Style::Posix => to_posix_path(s, mount_root),
+            Style::Windows => to_windows_path(s, mount_root),
+        };
+        match result {
+            Some(r) => r,
+            None => {
+                warnings.push(format!(
+                    "{}: {} has no {} equivalent, kept",
+                    key,
+                    s,
+                    to.name()
+                ));
+                s.to_string()
+            }
+        }
+    }
+
+    /// Lists the values of `config` that only work in the style `from`, when importing in `to`.
+    pub fn check(config: &Config, from: Style, to: Style) -> Vec<String> {
+        if from == to {
+            return Vec::new();
+        }
+
+        let mut warnings = vec![match &config.metadata {
+            Some(m) if !m.host.is_empty() => format!(
+                "the config was exported on {} ({}), use --translate to convert lists and paths",
+                m.platform, m.host
+            ),
+            Some(m) => format!(
+                "the config was exported on {}, use --translate to convert lists and paths",
+                m.platform
+            ),
+            None => format!(
+                "the config looks {}, use --translate to convert lists and paths",
+                from.name()
+            ),
+        }];
+        for e in config.data.iter() {
+            if e.delimiter == from.delimiter() {
+                warnings.push(format!("{}: delimited by {:?}", e.key, e.delimiter));
+            } else if is_foreign_path(&e.value, from) {
+                warnings.push(format!("{}: {} is a {} path", e.key, e.value, from.name()));
+            }
+        }
+
+        warnings
+    }
+
+    /// Converts the lists delimited in the style `from` and the paths in them, and the values
+    /// that are a single path, into the style `to`.
+    pub fn translate(
+        config: &Config,
+        from: Style,
+        to: Style,
+        mount_root: &str,
+    ) -> (Config, Vec<String>) {
+        let mut warnings: Vec<String> = Vec::new();
+        let mut data: Vec<ValueDetail> = Vec::new();
+
+        for e in config.data.iter() {
+            let mut entry = e.clone();
+            if from == to {
+                data.push(entry);
+                continue;
+            }
+
+            // `Path` and `PATH` are one variable on Windows but two on POSIX, and keys are
+            // looked up case-sensitively on import
+            if e.key.eq_ignore_ascii_case("PATH") {
+                entry.key = to.path_key().to_string();
+            }
+
+            if e.delimiter == from.delimiter() {
+                entry.delimiter = to.delimiter().to_string();
+                entry.value = e
+                    .value
+                    .split(from.delimiter())
+                    .map(|v| match is_foreign_path(v, from) {
+                        true => translate_path(&e.key, v, to, mount_root, &mut warnings),
+                        false => v.to_string(),
+                    })
+                    .collect::<Vec<String>>()
+                    .join(to.delimiter());
+            } else if is_foreign_path(&e.value, from) {
+                entry.value = translate_path(&e.key, &e.value, to, mount_root, &mut warnings);
+            }
+            data.push(entry);
+        }
+
+        (
+            Config {
+                version: config.version,
+                data,
+                metadata: config.metadata.clone(),
+            },
+            warnings,
+        )
+    }
+
+    /// Prepares a config exported elsewhere for the import here, translating it if enabled
+    /// and warning about it otherwise.
+    pub fn prepare(config: Config, translation: &Translation) -> (Config, Vec<String>) {
+        let to = current();
+        let from = source_style(&config);
+        let mut warnings = match to == Style::Windows || from == Some(Style::Windows) {
+            true => collisions(&config),
+            false => Vec::new(),
+        };
+
+        let from = match from {
+            Some(s) if s != to => s,
+            _ => return (config, warnings),
+        };
+        match translation.enabled {
+            true => {
+                let (result, w) = translate(&config, from, to, &translation.mount_root);
+                warnings.extend(w);
+                (result, warnings)
+            }
+            false => {
+                warnings.extend(check(&config, from, to));
+                (config, warnings)
+            }
+        }
+    }
+
+    /// Converts a config in the style `from` into the style to export in, recording it as
+    /// the platform of the metadata.
+    pub fn export(config: Config, from: Style, translation: &Translation) -> (Config, Vec<String>) {
+        let to = match translation.platform {
+            Some(s) => s,
+            None => return (config, Vec::new()),
+        };
+        let mut warnings = match to {
+            Style::Windows => collisions(&config),
+            Style::Posix => Vec::new(),
+        };
+        if from == to {
+            return (config, warnings);
+        }
+
+        let (mut result, w) = translate(&config, from, to, &translation.mount_root);
+        warnings.extend(w);
+        if let Some(m) = result.metadata.as_mut() {
+            m.platform = to.name().to_string();
+        }
+
+        (result, warnings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::json::config::Config;
+    use crate::portable::portable::{
+        Style, Translation, check, collisions, export, guess, parse_wsl_mount_root, to_posix_path,
+        to_windows_path, translate,
+    };
+
+    #[test]
+    fn test_paths() {
+        assert_eq!(
+            Some("/mnt/c/Tools/bin".to_string()),
+            to_posix_path("C:\\Tools\\bin\\", "/mnt")
+        );
+        assert_eq!(Some("/mnt/d".to_string()), to_posix_path("D:", "/mnt/"));
+        assert_eq!(None, to_posix_path("\\\\server\\share", "/mnt"));
+        assert_eq!(None, to_posix_path("/usr/bin", "/mnt"));
+
+        assert_eq!(
+            Some("C:\\Tools\\bin".to_string()),
+            to_windows_path("/mnt/c/Tools/bin", "/mnt")
+        );
+        assert_eq!(Some("D:\\".to_string()), to_windows_path("/mnt/d", "/mnt"));
+        assert_eq!(None, to_windows_path("/mnt/data/x", "/mnt"));
+        assert_eq!(None, to_windows_path("/usr/bin", "/mnt"));
+
+        let conf = "[boot]\nsystemd=true\n[automount]\nenabled = true\nroot = /win/\n";
+        assert_eq!(Some("/win/".to_string()), parse_wsl_mount_root(conf));
+        assert_eq!(None, parse_wsl_mount_root("[boot]\nroot = /x\n"));
+        assert_eq!(
+            Some("C:\\Tools".to_string()),
+            to_windows_path("/win/c/Tools", "/win/")
+        );
+    }
+
+    #[test]
+    fn test_translate() {
+        let json = r#"{"version":1,"data":[
+            {"key":"Path","value":"C:\\Tools\\bin;bin;\\\\server\\share","delimiter":";"},
+            {"key":"JAVA_HOME","value":"C:\\Java","overwrite":true},
+            {"key":"NAME","value":"x"}
+        ],"metadata":{"platform":"windows","host":"laptop"}}"#;
+        let config: Config = serde_json::from_str(json).unwrap();
+        assert_eq!(Some(Style::Windows), guess(&config));
+        assert_eq!(3, check(&config, Style::Windows, Style::Posix).len());
+
+        let (result, warnings) = translate(&config, Style::Windows, Style::Posix, "/mnt");
+        assert_eq!(
+            vec![
+                ("PATH", "/mnt/c/Tools/bin:bin:\\\\server\\share", ":"),
+                ("JAVA_HOME", "/mnt/c/Java", ""),
+                ("NAME", "x", ""),
+            ],
+            result
+                .data
+                .iter()
+                .map(|e| (e.key.as_str(), e.value.as_str(), e.delimiter.as_str()))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec!["Path: \\\\server\\share has no posix equivalent, kept"],
+            warnings
+        );
+    }
+
+    #[test]
+    fn test_export() {
+        let json = r#"{"version":1,"data":[
+            {"key":"PATH","value":"/home/a/bin:/mnt/d/tools","delimiter":":"},
+            {"key":"Path","value":"/usr/bin"}
+        ],"metadata":{"platform":"linux","host":"wsl"}}"#;
+        let config: Config = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            vec!["PATH and Path differ only in case, they are one variable on windows"],
+            collisions(&config)
+        );
+
+        let translation = Translation {
+            enabled: false,
+            platform: Some(Style::Windows),
+            mount_root: "/mnt".to_string(),
+        };
+        let (result, warnings) = export(config, Style::Posix, &translation);
+        assert_eq!("windows", result.metadata.unwrap().platform);
+        assert_eq!(
+            vec![
+                ("Path", "/home/a/bin;D:\\tools", ";"),
+                ("Path", "/usr/bin", "")
+            ],
+            result
+                .data
+                .iter()
+                .map(|e| (e.key.as_str(), e.value.as_str(), e.delimiter.as_str()))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![
+                "PATH and Path differ only in case, they are one variable on windows",
+                "PATH: /home/a/bin has no windows equivalent, kept",
+                "Path: /usr/bin has no windows equivalent, kept",
+            ],
+            warnings
+        );
+    }
+}
@@ -0,0 +1,123 @@
+pub mod probe {
+    use std::fs;
+    use std::path::Path;
+    use std::process::{Command, Stdio};
+
+    use crate::shellrc::shellrc::{canonical_shell_name, dump_env_command, shell_program};
+    use crate::utils;
+
+    /// Variables maintained by the shell itself, left out of the dumped environments.
+    pub const SHELL_KEYS: [&str; 4] = ["_", "SHLVL", "PWD", "OLDPWD"];
+
+    /// Splits the content of an environment separated by NUL, e.g., `env -0` or `/proc/<pid>/environ`.
+    pub fn parse_environ(data: &[u8]) -> Vec<(String, String)> {
+        data.split(|b| *b == 0)
+            .filter(|e| !e.is_empty())
+            .filter_map(|e| {
+                let s = String::from_utf8_lossy(e);
+                s.split_once('=')
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+            })
+            .collect()
+    }
+
+    /// Returns the arguments that keep `shell` from loading the user's startup files.
+    fn startup_args(shell: &str) -> Vec<&'static str> {
+        match shell {
+            "bash" => vec!["--norc", "--noprofile"],
+            "zsh" | "csh" | "tcsh" => vec!["-f"],
+            "fish" => vec!["--no-config"],
+            "nushell" => vec!["-n"],
+            "xonsh" => vec!["--no-rc"],
+            "elvish" => vec!["-norc"],
+            "powershell" => vec!["-NoProfile", "-NonInteractive", "-File"],
+            _ => Vec::new(),
+        }
+    }
+
+    fn script_name(shell: &str) -> &'static str {
+        match shell {
+            // PowerShell runs only files with the .ps1 extension
+            "powershell" => "probe.ps1",
+            "nushell" => "probe.nu",
+            "fish" => "probe.fish",
+            _ => "probe.sh",
+        }
+    }
+
+    fn run(
+        shell: &str,
+        env: &[(String, String)],
+        dir: &Path,
+        commands: &[String],
+    ) -> Result<(), String> {
+        let dump_path = |i: usize| dir.join(format!("env-{}", i)).to_string_lossy().to_string();
+
+        let mut lines: Vec<String> = Vec::new();
+        lines.push(dump_env_command(shell, &dump_path(0))?);
+        for (i, c) in commands.iter().enumerate() {
+            lines.push(c.to_string());
+            lines.push(dump_env_command(shell, &dump_path(i + 1))?);
+        }
+
+        let script = dir.join(script_name(shell));
+        if let Err(e) = fs::write(&script, format!("{}\n", lines.join("\n"))) {
+            return Err(format!("{}: {}", e, script.to_string_lossy()));
+        }
+
+        // the output of the commands must not mix with the output of envvar
+        let status = Command::new(shell_program(shell))
+            .args(startup_args(shell))
+            .arg(&script)
+            .env_clear()
+            .envs(
+                env.iter()
+                    .filter(|(k, _)| k != "BASH_ENV" && k != "ENV")
+                    .cloned(),
+            )
+            .stdin(Stdio::null())
+            .stdout(Stdio::from(std::io::stderr()))
+            .status();
+
+        match status {
+            Ok(s) if s.success() => Ok(()),
+            Ok(s) => Err(format!("{} exited with {}", shell_program(shell), s)),
+            Err(e) => Err(format!("{}: {}", e, shell_program(shell))),
+        }
+    }
+
+    /// Runs `commands` in a non-interactive `shell` started with `env`, and returns the
+    /// environment before the first command and after each of them.
+    pub fn probe(
+        shell: &str,
+        env: &[(String, String)],
+        commands: &[String],
+    ) -> Result<Vec<Vec<(String, String)>>, String> {
+        let shell = match canonical_shell_name(shell) {
+            Some(s) => s,
+            None => return Err(format!("unknown shell name: {}", shell)),
+        };
+
+        let dir = utils::utils::create_private_dir("envvar-probe")?;
+
+        let result = run(shell, env, &dir, commands).and_then(|_| {
+            let mut dumps: Vec<Vec<(String, String)>> = Vec::new();
+            for i in 0..=commands.len() {
+                let path = dir.join(format!("env-{}", i));
+                match fs::read(&path) {
+                    Ok(b) => dumps.push(
+                        parse_environ(&b)
+                            .into_iter()
+                            .filter(|(k, _)| !SHELL_KEYS.contains(&k.as_str()))
+                            .collect(),
+                    ),
+                    Err(e) => return Err(format!("{}: {}", e, path.to_string_lossy())),
+                }
+            }
+            Ok(dumps)
+        });
+        let _ = fs::remove_dir_all(&dir);
+
+        result
+    }
+}
@@ -0,0 +1,1157 @@
+pub mod rcparse {
+    use std::collections::{HashMap, HashSet};
+    use std::fs;
+    use std::path::Path;
+
+    use crate::envvar::environment_variable::env::PATH_DELIMITER;
+    use crate::format::format;
+    use crate::json::config::{Config, ValueDetail};
+    use crate::settings::settings::Settings;
+    use crate::shellrc::shellrc::canonical_shell_name;
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum Segment {
+        Text(String),
+        Var(String),
+    }
+
+    enum Statement {
+        /// Assigns an exported variable.
+        Set(String, Vec<Segment>),
+        /// Assigns a variable that is exported only if it already is.
+        Assign(String, Vec<Segment>),
+        /// Exports a variable assigned before.
+        Export(String),
+        Unset(String),
+    }
+
+    #[derive(Clone, Copy, PartialEq)]
+    enum Dialect {
+        Posix,
+        Csh,
+        Fish,
+    }
+
+    struct Word {
+        /// The name of an assignment, e.g., `FOO` of `FOO=bar`.
+        name: Option<String>,
+        value: Vec<Segment>,
+    }
+
+    impl Word {
+        fn text(&self) -> String {
+            self.value
+                .iter()
+                .map(|s| match s {
+                    Segment::Text(t) => t.to_string(),
+                    Segment::Var(v) => format!("${}", v),
+                })
+                .collect()
+        }
+    }
+
+    fn push_char(segments: &mut Vec<Segment>, c: char) {
+        match segments.last_mut() {
+            Some(Segment::Text(s)) => s.push(c),
+            _ => segments.push(Segment::Text(c.to_string())),
+        }
+    }
+
+    fn is_name_char(c: char) -> bool {
+        c.is_ascii_alphanumeric() || c == '_'
+    }
+
+    /// Reads `$NAME` or `${NAME}` at `i`, returning `None` for a lone `$`.
+    fn read_variable(chars: &[char], i: &mut usize) -> Option<String> {
+        let mut j = *i + 1;
+        if chars.get(j) == Some(&'{') {
+            let end = chars[j..].iter().position(|c| *c == '}')? + j;
+            // modifiers such as ${NAME:-default} are not supported
+            let name = chars[j + 1..end]
+                .iter()
+                .take_while(|c| is_name_char(**c))
+                .collect::<String>();
+            if name.is_empty() {
+                return None;
+            }
+            *i = end + 1;
+            return Some(name);
+        }
+
+        while j < chars.len() && is_name_char(chars[j]) {
+            j += 1;
+        }
+        if j == *i + 1 {
+            return None;
+        }
+
+        let name = chars[*i + 1..j].iter().collect::<String>();
+        *i = j;
+        Some(name)
+    }
+
+    fn is_word_break(c: char) -> bool {
+        c.is_whitespace() || ";&|".contains(c)
+    }
+
+    /// Splits `content` into commands of words, handling quotes and variable references.
+    fn tokenize(content: &str, dialect: Dialect) -> Vec<Vec<Word>> {
+        let chars = content.chars().collect::<Vec<char>>();
+        let mut commands: Vec<Vec<Word>> = Vec::new();
+        let mut words: Vec<Word> = Vec::new();
+        let mut i = 0;
+
+        while i < chars.len() {
+            let c = chars[i];
+            if c == '\\' && chars.get(i + 1) == Some(&'\n') {
+                i += 2;
+                continue;
+            }
+            if c == '\n' || ";&|".contains(c) {
+                if !words.is_empty() {
+                    commands.push(std::mem::take(&mut words));
+                }
+                i += 1;
+                continue;
+            }
+            if c.is_whitespace() {
+                i += 1;
+                continue;
+            }
+            if c == '#' {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+                continue;
+            }
+
+            let mut word = Word {
+                name: None,
+                value: Vec::new(),
+            };
+            if dialect == Dialect::Posix && !c.is_ascii_digit() {
+                let mut j = i;
+                while j < chars.len() && is_name_char(chars[j]) {
+                    j += 1;
+                }
+                if j > i && chars.get(j) == Some(&'=') {
+                    word.name = Some(chars[i..j].iter().collect());
+                    i = j + 1;
+                }
+            }
+
+            while i < chars.len() && !is_word_break(chars[i]) {
+                match chars[i] {
+                    '\'' => {
+                        i += 1;
+                        while i < chars.len() && chars[i] != '\'' {
+                            let next = chars.get(i + 1).copied();
+                            let is_escape = match (dialect, next) {
+                                (Dialect::Fish, Some('\\' | '\'')) => true,
+                                // history substitution and newlines are escaped even in quotes
+                                (Dialect::Csh, Some('!' | '\n')) => true,
+                                _ => false,
+                            };
+                            if chars[i] == '\\' && is_escape {
+                                i += 1;
+                            }
+                            push_char(&mut word.value, chars[i]);
+                            i += 1;
+                        }
+                        i += 1;
+                    }
+                    '"' => {
+                        i += 1;
+                        while i < chars.len() && chars[i] != '"' {
+                            if chars[i] == '\\' && i + 1 < chars.len() {
+                                if chars[i + 1] == '\n' {
+                                    i += 2;
+                                    continue;
+                                }
+                                if "\"\\$`".contains(chars[i + 1]) {
+                                    push_char(&mut word.value, chars[i + 1]);
+                                    i += 2;
+                                    continue;
+                                }
+                            }
+                            if chars[i] == '$'
+                                && let Some(name) = read_variable(&chars, &mut i)
+                            {
+                                word.value.push(Segment::Var(name));
+                                continue;
+                            }
+                            push_char(&mut word.value, chars[i]);
+                            i += 1;
+                        }
+                        i += 1;
+                    }
+                    '\\' => {
+                        if let Some(c) = chars.get(i + 1) {
+                            push_char(&mut word.value, *c);
+                        }
+                        i += 2;
+                    }
+                    '$' => match read_variable(&chars, &mut i) {
+                        Some(name) => word.value.push(Segment::Var(name)),
+                        None => {
+                            push_char(&mut word.value, '$');
+                            i += 1;
+                        }
+                    },
+                    // a tilde is expanded at the start of a word, and after a colon in assignments
+                    '~' if matches!(chars.get(i + 1), None | Some('/' | ':'))
+                        && match word.value.last() {
+                            None => true,
+                            Some(Segment::Text(t)) => dialect == Dialect::Posix && t.ends_with(':'),
+                            Some(Segment::Var(_)) => false,
+                        } =>
+                    {
+                        word.value.push(Segment::Var("HOME".to_string()));
+                        i += 1;
+                    }
+                    c => {
+                        push_char(&mut word.value, c);
+                        i += 1;
+                    }
+                }
+            }
+
+            words.push(word);
+        }
+
+        if !words.is_empty() {
+            commands.push(words);
+        }
+
+        commands
+    }
+
+    fn parse_posix(content: &str) -> Vec<Statement> {
+        let mut result: Vec<Statement> = Vec::new();
+
+        for words in tokenize(content, Dialect::Posix) {
+            // assignments before a command only apply to that command
+            if words.iter().all(|w| w.name.is_some()) {
+                for w in words {
+                    result.push(Statement::Assign(w.name.unwrap(), w.value));
+                }
+                continue;
+            }
+
+            let command = words[0].text();
+            let is_export = match command.as_str() {
+                // `export -n` removes the export attribute
+                "export" => !words.iter().any(|w| w.name.is_none() && w.text() == "-n"),
+                "declare" | "typeset" => words.iter().any(|w| w.name.is_none() && w.text() == "-x"),
+                "unset" => {
+                    for w in words[1..].iter().filter(|w| !w.text().starts_with('-')) {
+                        result.push(Statement::Unset(w.text()));
+                    }
+                    continue;
+                }
+                _ => false,
+            };
+            if !is_export {
+                continue;
+            }
+
+            for w in words.into_iter().skip(1) {
+                match w.name {
+                    Some(name) => result.push(Statement::Set(name, w.value)),
+                    None if w.text().starts_with('-') => {}
+                    None => result.push(Statement::Export(w.text())),
+                }
+            }
+        }
+
+        result
+    }
+
+    fn parse_csh(content: &str) -> Vec<Statement> {
+        let mut result: Vec<Statement> = Vec::new();
+
+        for words in tokenize(content, Dialect::Csh) {
+            match (words[0].text().as_str(), words.get(1)) {
+                ("setenv", Some(name)) => {
+                    let value = match words.get(2) {
+                        Some(w) => w.value.clone(),
+                        None => Vec::new(),
+                    };
+                    result.push(Statement::Set(name.text(), value));
+                }
+                ("unsetenv", _) => {
+                    for w in words[1..].iter() {
+                        result.push(Statement::Unset(w.text()));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        result
+    }
+
+    /// Joins the elements of a list with `delimiter`, keeping references as they are.
+    fn join_elements(elements: Vec<Vec<Segment>>, delimiter: &str) -> Vec<Segment> {
+        let mut result: Vec<Segment> = Vec::new();
+        for (i, e) in elements.into_iter().enumerate() {
+            if i > 0 {
+                for c in delimiter.chars() {
+                    push_char(&mut result, c);
+                }
+            }
+            for s in e {
+                match s {
+                    Segment::Text(t) => t.chars().for_each(|c| push_char(&mut result, c)),
+                    v => result.push(v),
+                }
+            }
+        }
+
+        result
+    }
+
+    fn parse_fish(content: &str) -> Vec<Statement> {
+        let mut result: Vec<Statement> = Vec::new();
+
+        for words in tokenize(content, Dialect::Fish) {
+            if words[0].text() != "set" {
+                continue;
+            }
+
+            let mut flags = String::new();
+            let mut rest = words.into_iter().skip(1).peekable();
+            while let Some(w) = rest.next_if(|w| w.text().starts_with('-')) {
+                match w.text().as_str() {
+                    "--export" => flags.push('x'),
+                    "--erase" => flags.push('e'),
+                    "--append" => flags.push('a'),
+                    "--prepend" => flags.push('p'),
+                    s if !s.starts_with("--") => flags.push_str(&s[1..]),
+                    _ => {}
+                }
+            }
+
+            let name = match rest.next() {
+                Some(w) => w.text(),
+                None => continue,
+            };
+            if flags.contains('e') {
+                result.push(Statement::Unset(name));
+                continue;
+            }
+
+            let mut elements = rest.map(|w| w.value).collect::<Vec<Vec<Segment>>>();
+            if flags.contains('a') {
+                elements.insert(0, vec![Segment::Var(name.to_string())]);
+            }
+            if flags.contains('p') {
+                elements.push(vec![Segment::Var(name.to_string())]);
+            }
+
+            // fish exports a list joined with colons for PATH variables, spaces for others
+            let delimiter = if name.ends_with("PATH") {
+                PATH_DELIMITER
+            } else {
+                " "
+            };
+            let value = join_elements(elements, delimiter);
+            if flags.contains('x') {
+                result.push(Statement::Set(name, value));
+            } else {
+                result.push(Statement::Assign(name, value));
+            }
+        }
+
+        result
+    }
+
+    fn skip_spaces(chars: &[char], i: &mut usize) {
+        while *i < chars.len() && chars[*i].is_whitespace() {
+            *i += 1;
+        }
+    }
+
+    fn starts_with_at(chars: &[char], i: usize, s: &str) -> bool {
+        let s = s.chars().collect::<Vec<char>>();
+        chars.len() >= i + s.len()
+            && chars[i..i + s.len()]
+                .iter()
+                .zip(s.iter())
+                .all(|(a, b)| a.eq_ignore_ascii_case(b))
+    }
+
+    /// Reads `$env:NAME` or `${env:NAME}` at `i`.
+    fn read_powershell_variable(chars: &[char], i: &mut usize) -> Option<String> {
+        if starts_with_at(chars, *i, "${env:") {
+            let mut j = *i + 6;
+            let mut name = String::new();
+            while j < chars.len() && chars[j] != '}' {
+                if chars[j] == '`' && j + 1 < chars.len() {
+                    j += 1;
+                }
+                name.push(chars[j]);
+                j += 1;
+            }
+            *i = j + 1;
+            return Some(name);
+        }
+
+        if starts_with_at(chars, *i, "$env:") {
+            let mut j = *i + 5;
+            while j < chars.len() && is_name_char(chars[j]) {
+                j += 1;
+            }
+            let name = chars[*i + 5..j].iter().collect::<String>();
+            *i = j;
+            return Some(name);
+        }
+
+        None
+    }
+
+    fn parse_powershell_term(chars: &[char], i: &mut usize) -> Result<Vec<Segment>, String> {
+        skip_spaces(chars, i);
+        let mut result: Vec<Segment> = Vec::new();
+
+        match chars.get(*i) {
+            Some('\'') => {
+                *i += 1;
+                while *i < chars.len() {
+                    if chars[*i] == '\'' {
+                        if chars.get(*i + 1) != Some(&'\'') {
+                            break;
+                        }
+                        *i += 1;
+                    }
+                    push_char(&mut result, chars[*i]);
+                    *i += 1;
+                }
+                *i += 1;
+            }
+            Some('"') => {
+                *i += 1;
+                while *i < chars.len() {
+                    match chars[*i] {
+                        '"' if chars.get(*i + 1) == Some(&'"') => *i += 1,
+                        '"' => break,
+                        '`' if *i + 1 < chars.len() => {
+                            *i += 1;
+                            let c = match chars[*i] {
+                                'n' => '\n',
+                                't' => '\t',
+                                'r' => '\r',
+                                '0' => '\0',
+                                c => c,
+                            };
+                            push_char(&mut result, c);
+                            *i += 1;
+                            continue;
+                        }
+                        '$' => {
+                            if let Some(name) = read_powershell_variable(chars, i) {
+                                result.push(Segment::Var(name));
+                                continue;
+                            }
+                        }
+                        _ => {}
+                    }
+                    push_char(&mut result, chars[*i]);
+                    *i += 1;
+                }
+                *i += 1;
+            }
+            Some('$') => match read_powershell_variable(chars, i) {
+                Some(name) => result.push(Segment::Var(name)),
+                None => return Err("unsupported variable".to_string()),
+            },
+            Some('[') => {
+                let separator = [
+                    "[IO.Path]::PathSeparator",
+                    "[System.IO.Path]::PathSeparator",
+                ]
+                .iter()
+                .find(|s| starts_with_at(chars, *i, s));
+                match separator {
+                    Some(s) => {
+                        *i += s.len();
+                        result.push(Segment::Text(PATH_DELIMITER.to_string()));
+                    }
+                    None => return Err("unsupported type expression".to_string()),
+                }
+            }
+            Some('@') if chars.get(*i + 1) == Some(&'(') => {
+                *i += 2;
+                let mut elements: Vec<Vec<Segment>> = Vec::new();
+                loop {
+                    skip_spaces(chars, i);
+                    match chars.get(*i) {
+                        Some(')') => break,
+                        Some(',') => *i += 1,
+                        Some(_) => elements.push(parse_powershell_expression(chars, i)?),
+                        None => return Err("unterminated array".to_string()),
+                    }
+                }
+                *i += 1;
+
+                skip_spaces(chars, i);
+                let delimiter = if starts_with_at(chars, *i, "-join") {
+                    *i += 5;
+                    parse_powershell_term(chars, i)?
+                } else {
+                    vec![Segment::Text(" ".to_string())]
+                };
+                let delimiter = match delimiter.as_slice() {
+                    [Segment::Text(t)] => t.to_string(),
+                    _ => return Err("unsupported delimiter".to_string()),
+                };
+                result = join_elements(elements, &delimiter);
+            }
+            Some('(') => {
+                *i += 1;
+                result = parse_powershell_expression(chars, i)?;
+                skip_spaces(chars, i);
+                if chars.get(*i) != Some(&')') {
+                    return Err("unterminated parenthesis".to_string());
+                }
+                *i += 1;
+            }
+            _ => return Err("unsupported expression".to_string()),
+        }
+
+        Ok(result)
+    }
+
+    /// Parses terms joined with `+`.
+    fn parse_powershell_expression(chars: &[char], i: &mut usize) -> Result<Vec<Segment>, String> {
+        let mut result = parse_powershell_term(chars, i)?;
+        loop {
+            skip_spaces(chars, i);
+            if chars.get(*i) != Some(&'+') {
+                return Ok(result);
+            }
+            *i += 1;
+            result = join_elements(vec![result, parse_powershell_term(chars, i)?], "");
+        }
+    }
+
+    fn parse_powershell(content: &str) -> Result<Vec<Statement>, String> {
+        let mut result: Vec<Statement> = Vec::new();
+
+        for (n, line) in content.lines().enumerate() {
+            let chars = line.trim().chars().collect::<Vec<char>>();
+            let mut i = 0;
+
+            if starts_with_at(&chars, 0, "Remove-Item") {
+                let line = line.trim();
+                // lowercasing may change the byte length, so the offset is searched in `line`
+                let found = line.char_indices().map(|(p, _)| p).find(|p| {
+                    line[*p..]
+                        .get(..4)
+                        .is_some_and(|s| s.eq_ignore_ascii_case("env:"))
+                });
+                if let Some(p) = found {
+                    let name = line[p + 4..]
+                        .trim_start_matches('\\')
+                        .chars()
+                        .take_while(|c| !c.is_whitespace() && *c != '\'' && *c != '"')
+                        .collect::<String>();
+                    result.push(Statement::Unset(name));
+                }
+                continue;
+            }
+
+            let name = match read_powershell_variable(&chars, &mut i) {
+                Some(s) => s,
+                None => continue,
+            };
+            skip_spaces(&chars, &mut i);
+            let is_append = chars.get(i) == Some(&'+');
+            if is_append {
+                i += 1;
+            }
+            if chars.get(i) != Some(&'=') {
+                continue;
+            }
+            i += 1;
+
+            let mut value = match parse_powershell_expression(&chars, &mut i) {
+                Ok(v) => v,
+                Err(e) => return Err(format!("line {}: {}", n + 1, e)),
+            };
+            if is_append {
+                value = join_elements(vec![vec![Segment::Var(name.to_string())], value], "");
+            }
+            result.push(Statement::Set(name, value));
+        }
+
+        Ok(result)
+    }
+
+    enum NuValue {
+        Str(Vec<Segment>),
+        List(Vec<Vec<Segment>>),
+    }
+
+    impl NuValue {
+        fn into_list(self) -> Vec<Vec<Segment>> {
+            match self {
+                NuValue::Str(s) => vec![s],
+                NuValue::List(l) => l,
+            }
+        }
+    }
+
+    fn is_nushell_break(c: char) -> bool {
+        c.is_whitespace() || "[](),|".contains(c)
+    }
+
+    fn parse_nushell_string(chars: &[char], i: &mut usize) -> Vec<Segment> {
+        let mut result: Vec<Segment> = Vec::new();
+        let quote = chars[*i];
+        *i += 1;
+        while *i < chars.len() && chars[*i] != quote {
+            if quote == '"' && chars[*i] == '\\' && *i + 1 < chars.len() {
+                *i += 1;
+                let c = match chars[*i] {
+                    'n' => '\n',
+                    't' => '\t',
+                    'r' => '\r',
+                    c => c,
+                };
+                push_char(&mut result, c);
+            } else {
+                push_char(&mut result, chars[*i]);
+            }
+            *i += 1;
+        }
+        *i += 1;
+
+        result
+    }
+
+    /// Reads `$env.NAME` or a quoted name after `$env.` at `i`.
+    fn read_nushell_variable(chars: &[char], i: &mut usize) -> Option<String> {
+        if !starts_with_at(chars, *i, "$env.") {
+            return None;
+        }
+
+        *i += 5;
+        match chars.get(*i) {
+            Some('"' | '\'' | '`') => Some(
+                parse_nushell_string(chars, i)
+                    .iter()
+                    .map(|s| match s {
+                        Segment::Text(t) => t.to_string(),
+                        Segment::Var(v) => v.to_string(),
+                    })
+                    .collect(),
+            ),
+            _ => {
+                let start = *i;
+                while *i < chars.len() && !is_nushell_break(chars[*i]) && chars[*i] != '=' {
+                    *i += 1;
+                }
+                Some(chars[start..*i].iter().collect())
+            }
+        }
+    }
+
+    fn parse_nushell_value(chars: &[char], i: &mut usize) -> Result<NuValue, String> {
+        skip_spaces(chars, i);
+
+        match chars.get(*i) {
+            Some('"' | '\'' | '`') => Ok(NuValue::Str(parse_nushell_string(chars, i))),
+            Some('$') => match read_nushell_variable(chars, i) {
+                Some(name) => Ok(NuValue::Str(vec![Segment::Var(name)])),
+                None => Err("unsupported variable".to_string()),
+            },
+            Some('[') => {
+                *i += 1;
+                let mut elements: Vec<Vec<Segment>> = Vec::new();
+                loop {
+                    skip_spaces(chars, i);
+                    match chars.get(*i) {
+                        Some(']') => break,
+                        Some(',') => *i += 1,
+                        Some(_) => elements.extend(parse_nushell_value(chars, i)?.into_list()),
+                        None => return Err("unterminated list".to_string()),
+                    }
+                }
+                *i += 1;
+                Ok(NuValue::List(elements))
+            }
+            Some('(') => {
+                *i += 1;
+                let value = parse_nushell_pipeline(chars, i)?;
+                skip_spaces(chars, i);
+                if chars.get(*i) != Some(&')') {
+                    return Err("unterminated parenthesis".to_string());
+                }
+                *i += 1;
+                Ok(value)
+            }
+            Some(_) => {
+                let start = *i;
+                while *i < chars.len() && !is_nushell_break(chars[*i]) {
+                    *i += 1;
+                }
+                Ok(NuValue::Str(vec![Segment::Text(
+                    chars[start..*i].iter().collect(),
+                )]))
+            }
+            None => Err("missing value".to_string()),
+        }
+    }
+
+    /// Skips a command of a pipeline that does not change the elements, e.g., `split row`.
+    fn skip_nushell_command(chars: &[char], i: &mut usize) {
+        let mut depth = 0;
+        while *i < chars.len() {
+            match chars[*i] {
+                '(' | '[' => depth += 1,
+                ')' | ']' if depth == 0 => return,
+                ')' | ']' => depth -= 1,
+                '|' if depth == 0 => return,
+                _ => {}
+            }
+            *i += 1;
+        }
+    }
+
+    fn parse_nushell_pipeline(chars: &[char], i: &mut usize) -> Result<NuValue, String> {
+        let mut value = parse_nushell_value(chars, i)?;
+
+        loop {
+            skip_spaces(chars, i);
+            if chars.get(*i) != Some(&'|') {
+                return Ok(value);
+            }
+            *i += 1;
+            skip_spaces(chars, i);
+
+            let start = *i;
+            while *i < chars.len() && !is_nushell_break(chars[*i]) {
+                *i += 1;
+            }
+            let command = chars[start..*i].iter().collect::<String>();
+            value = match command.as_str() {
+                "prepend" => {
+                    let mut list = parse_nushell_value(chars, i)?.into_list();
+                    list.extend(value.into_list());
+                    NuValue::List(list)
+                }
+                "append" => {
+                    let mut list = value.into_list();
+                    list.extend(parse_nushell_value(chars, i)?.into_list());
+                    NuValue::List(list)
+                }
+                "split" | "uniq" => {
+                    skip_nushell_command(chars, i);
+                    value
+                }
+                _ => return Err(format!("unsupported command: {}", command)),
+            };
+        }
+    }
+
+    fn parse_nushell(content: &str) -> Result<Vec<Statement>, String> {
+        let mut result: Vec<Statement> = Vec::new();
+
+        for (n, line) in content.lines().enumerate() {
+            let chars = line.trim().chars().collect::<Vec<char>>();
+            let mut i = 0;
+
+            if starts_with_at(&chars, 0, "hide-env ") {
+                let words = line.split_whitespace().skip(1);
+                for w in words.filter(|w| !w.starts_with('-')) {
+                    result.push(Statement::Unset(w.trim_matches(['"', '\'']).to_string()));
+                }
+                continue;
+            }
+
+            let name = match read_nushell_variable(&chars, &mut i) {
+                Some(s) => s,
+                None => continue,
+            };
+            skip_spaces(&chars, &mut i);
+            if chars.get(i) != Some(&'=') {
+                continue;
+            }
+            i += 1;
+
+            let value = match parse_nushell_pipeline(&chars, &mut i) {
+                Ok(NuValue::Str(s)) => s,
+                Ok(NuValue::List(l)) => join_elements(l, PATH_DELIMITER),
+                Err(e) => return Err(format!("line {}: {}", n + 1, e)),
+            };
+            result.push(Statement::Set(name, value));
+        }
+
+        Ok(result)
+    }
+
+    /// Builds a config from the statements, tracking the values the shell would see.
+    struct Builder {
+        values: HashMap<String, String>,
+        exported: HashSet<String>,
+        /// Variables assigned in the file but not exported yet.
+        unexported: HashSet<String>,
+        data: Vec<ValueDetail>,
+        warnings: Vec<String>,
+    }
+
+    impl Builder {
+        fn expand(&mut self, key: &str, segments: &[Segment]) -> String {
+            let mut result = String::new();
+            for s in segments {
+                match s {
+                    Segment::Text(t) => result.push_str(t),
+                    Segment::Var(v) => match self.values.get(v) {
+                        Some(value) => result.push_str(value),
+                        None => self
+                            .warnings
+                            .push(format!("{}: ${} is not set, expanded to empty", key, v)),
+                    },
+                }
+            }
+
+            result
+        }
+
+        /// Returns the delimiter of `prefix$KEY suffix`, if it extends a list.
+        fn list_delimiter(prefix: &str, suffix: &str) -> Option<char> {
+            let d = match (prefix.chars().last(), suffix.chars().next()) {
+                (Some(c), _) => c,
+                (None, Some(c)) => c,
+                (None, None) => return None,
+            };
+            let is_list = (d == ':' || d == ';')
+                && (prefix.is_empty() || prefix.ends_with(d))
+                && (suffix.is_empty() || suffix.starts_with(d));
+
+            if is_list { Some(d) } else { None }
+        }
+
+        fn overwrite(&mut self, key: &str, value: &str) {
+            self.data.retain(|e| e.key != key);
+            self.data.push(ValueDetail {
+                key: key.to_string(),
+                value: value.to_string(),
+                overwrite: true,
+                delimiter: "".to_string(),
+                insert: -1,
+                secret: false,
+            });
+        }
+
+        fn set(&mut self, key: &str, segments: &[Segment]) {
+            self.exported.insert(key.to_string());
+            self.unexported.remove(key);
+
+            let own = segments
+                .iter()
+                .enumerate()
+                .filter(|(_, s)| **s == Segment::Var(key.to_string()))
+                .map(|(i, _)| i)
+                .collect::<Vec<usize>>();
+
+            if let [i] = own.as_slice() {
+                let prefix = self.expand(key, &segments[..*i]);
+                let suffix = self.expand(key, &segments[i + 1..]);
+
+                if let Some(d) = Self::list_delimiter(&prefix, &suffix) {
+                    let current = self.values.get(key).cloned().unwrap_or_default();
+                    let value = if current.is_empty() {
+                        format!("{}{}", prefix, suffix).trim_matches(d).to_string()
+                    } else {
+                        format!("{}{}{}", prefix, current, suffix)
+                    };
+
+                    // a value assigned in the file is still a plain assignment
+                    let last = self.data.iter_mut().rev().find(|e| e.key == key);
+                    if let Some(e) = last
+                        && e.overwrite
+                    {
+                        e.value = value.to_string();
+                    } else {
+                        let prefix = prefix.trim_end_matches(d);
+                        let suffix = suffix.trim_start_matches(d);
+                        for (value, insert) in [(prefix, 0), (suffix, -1)] {
+                            if value.is_empty() {
+                                continue;
+                            }
+                            self.data.push(ValueDetail {
+                                key: key.to_string(),
+                                value: value.to_string(),
+                                overwrite: false,
+                                delimiter: d.to_string(),
+                                insert,
+                                secret: false,
+                            });
+                        }
+                    }
+
+                    self.values.insert(key.to_string(), value);
+                    return;
+                }
+            }
+
+            let value = self.expand(key, segments);
+            self.overwrite(key, &value);
+            self.values.insert(key.to_string(), value);
+        }
+
+        fn apply(&mut self, statement: Statement) {
+            match statement {
+                Statement::Set(key, segments) => self.set(&key, &segments),
+                Statement::Assign(key, segments) => {
+                    if self.exported.contains(&key) {
+                        self.set(&key, &segments);
+                    } else {
+                        let value = self.expand(&key, &segments);
+                        self.values.insert(key.to_string(), value);
+                        self.unexported.insert(key);
+                    }
+                }
+                Statement::Export(key) => {
+                    if self.unexported.remove(&key) {
+                        let value = self.values.get(&key).cloned().unwrap_or_default();
+                        self.overwrite(&key, &value);
+                    }
+                    self.exported.insert(key);
+                }
+                Statement::Unset(key) => {
+                    self.warnings.push(format!(
+                        "{}: removing a variable cannot be expressed in a config, skipped",
+                        key
+                    ));
+                    self.data.retain(|e| e.key != key);
+                    self.values.remove(&key);
+                }
+            }
+        }
+    }
+
+    /// Converts the rc file `content` written for `shell` into a config. References to
+    /// other variables are expanded with `env`, as the shell would when loading the file.
+    /// Returns the config and the warnings about what could not be converted.
+    pub fn parse_rc(
+        shell: &str,
+        content: &str,
+        env: &Vec<(String, String)>,
+    ) -> Result<(Config, Vec<String>), String> {
+        let statements = match canonical_shell_name(shell) {
+            Some("bash" | "zsh" | "dash" | "ksh" | "mksh") => parse_posix(content),
+            Some("csh" | "tcsh") => parse_csh(content),
+            Some("fish") => parse_fish(content),
+            Some("powershell") => parse_powershell(content)?,
+            Some("nushell") => parse_nushell(content)?,
+            _ => return Err(format!("parsing is not supported for {}", shell)),
+        };
+
+        let mut builder = Builder {
+            values: env.iter().cloned().collect(),
+            exported: env.iter().map(|(k, _)| k.to_string()).collect(),
+            unexported: HashSet::new(),
+            data: Vec::new(),
+            warnings: Vec::new(),
+        };
+        for s in statements {
+            builder.apply(s);
+        }
+
+        let config = Config {
+            version: 1,
+            data: builder.data,
+            metadata: None,
+        };
+
+        Ok((config, builder.warnings))
+    }
+
+    /// Guesses the shell from the name of an rc file, e.g., `config.fish`.
+    pub fn shell_from_filename(path: &Path) -> Option<&'static str> {
+        let name = path.file_name()?.to_string_lossy().to_lowercase();
+        let extension = path.extension().map(|e| e.to_string_lossy().to_lowercase());
+
+        match extension.as_deref() {
+            Some("fish") => return Some("fish"),
+            Some("ps1") => return Some("powershell"),
+            Some("nu") => return Some("nushell"),
+            Some("csh") => return Some("csh"),
+            _ => {}
+        }
+
+        ["tcsh", "csh", "zsh", "ksh", "mksh", "bash"]
+            .iter()
+            .find(|s| name.contains(*s))
+            .copied()
+            .or(if name == ".login" { Some("csh") } else { None })
+    }
+
+    /// Converts the rc file given as the operand into a config.
+    pub fn from_rc(settings: &Settings) -> Result<(), String> {
+        let filepath = match settings.operands.first() {
+            Some(s) => *s,
+            None => return Err("no rc file specified".to_string()),
+        };
+
+        let shell = match settings.shell_rc.shell {
+            Some(s) => s,
+            None => shell_from_filename(Path::new(filepath)).unwrap_or("bash"),
+        };
+        let content = match fs::read_to_string(filepath) {
+            Ok(s) => s,
+            Err(e) => return Err(format!("{}: {}", e, filepath)),
+        };
+
+        let env = std::env::vars().collect::<Vec<(String, String)>>();
+        let (config, warnings) = parse_rc(shell, &content, &env)?;
+        for w in warnings.iter() {
+            eprintln!("warning: {}", w);
+        }
+
+        match settings.export {
+            Some(s) => format::write_config(&config, Path::new(s), settings.format),
+            None => format::print_config(&config, settings.format),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::json::config::ValueDetail;
+    use crate::rcparse::rcparse::parse_rc;
+
+    fn env() -> Vec<(String, String)> {
+        vec![
+            ("HOME".to_string(), "/home/user".to_string()),
+            ("PATH".to_string(), "/usr/bin".to_string()),
+        ]
+    }
+
+    fn entry(data: &[ValueDetail], key: &str) -> Vec<(String, bool, String, i32)> {
+        data.iter()
+            .filter(|e| e.key == key)
+            .map(|e| {
+                (
+                    e.value.to_string(),
+                    e.overwrite,
+                    e.delimiter.to_string(),
+                    e.insert,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_posix() {
+        let content = r#"
+# comment
+export EDITOR=vim
+export GREETING='it'\''s "quoted"'
+export PATH="$HOME/bin:$PATH"
+PATH=$PATH:/opt/tools/bin
+LOCAL=value
+MANPATH=~/man; export MANPATH
+export -n IGNORED
+alias ll='ls -l'
+"#;
+        let (config, warnings) = parse_rc("bash", content, &env()).unwrap();
+        assert!(warnings.is_empty());
+
+        assert_eq!(
+            vec![("vim".to_string(), true, "".to_string(), -1)],
+            entry(&config.data, "EDITOR")
+        );
+        assert_eq!(
+            vec![("it's \"quoted\"".to_string(), true, "".to_string(), -1)],
+            entry(&config.data, "GREETING")
+        );
+        assert_eq!(
+            vec![
+                ("/home/user/bin".to_string(), false, ":".to_string(), 0),
+                ("/opt/tools/bin".to_string(), false, ":".to_string(), -1),
+            ],
+            entry(&config.data, "PATH")
+        );
+        assert!(entry(&config.data, "LOCAL").is_empty());
+        assert_eq!(
+            vec![("/home/user/man".to_string(), true, "".to_string(), -1)],
+            entry(&config.data, "MANPATH")
+        );
+    }
+
+    #[test]
+    fn test_parse_fish_and_csh() {
+        let content = "set -gx PATH /opt/bin $PATH\nset -gx FOO 'a\\'b'\nset -e BAR\n";
+        let (config, warnings) = parse_rc("fish", content, &env()).unwrap();
+        assert_eq!(1, warnings.len());
+        assert_eq!(
+            vec![("/opt/bin".to_string(), false, ":".to_string(), 0)],
+            entry(&config.data, "PATH")
+        );
+        assert_eq!(
+            vec![("a'b".to_string(), true, "".to_string(), -1)],
+            entry(&config.data, "FOO")
+        );
+
+        let content = "setenv FOO 'wow\\!'\nsetenv PATH ${PATH}:/opt/bin\n";
+        let (config, _) = parse_rc("tcsh", content, &env()).unwrap();
+        assert_eq!(
+            vec![("wow!".to_string(), true, "".to_string(), -1)],
+            entry(&config.data, "FOO")
+        );
+        assert_eq!(
+            vec![("/opt/bin".to_string(), false, ":".to_string(), -1)],
+            entry(&config.data, "PATH")
+        );
+    }
+
+    #[test]
+    fn test_parse_powershell_and_nushell() {
+        let content = "$env:FOO = 'it''s'\n$env:PATH = @('/opt/bin', $env:PATH) -join [IO.Path]::PathSeparator\n$env:BAR = \"$env:HOME/bar\"\n";
+        let (config, _) = parse_rc("pwsh", content, &env()).unwrap();
+        assert_eq!(
+            vec![("it's".to_string(), true, "".to_string(), -1)],
+            entry(&config.data, "FOO")
+        );
+        assert_eq!(
+            vec![("/opt/bin".to_string(), false, ":".to_string(), 0)],
+            entry(&config.data, "PATH")
+        );
+        assert_eq!(
+            vec![("/home/user/bar".to_string(), true, "".to_string(), -1)],
+            entry(&config.data, "BAR")
+        );
+        assert!(parse_rc("pwsh", "Remove-Item -Path 'İİİİİİİİİİ' Env:FOO\n", &env()).is_ok());
+
+        let content = "$env.FOO = \"a\\tb\"\n$env.PATH = ($env.PATH | split row (char esep) | prepend '/opt/bin' | append [/usr/local/bin])\n";
+        let (config, _) = parse_rc("nu", content, &env()).unwrap();
+        assert_eq!(
+            vec![("a\tb".to_string(), true, "".to_string(), -1)],
+            entry(&config.data, "FOO")
+        );
+        assert_eq!(
+            vec![
+                ("/opt/bin".to_string(), false, ":".to_string(), 0),
+                ("/usr/local/bin".to_string(), false, ":".to_string(), -1),
+            ],
+            entry(&config.data, "PATH")
+        );
+    }
+
+    #[test]
+    fn test_parse_generated() {
+        let content =
+            "# created by envvar\nexport FOO='a b'\nexport PATH=/opt/bin:/usr/bin\nunset BAR\n";
+        let (config, warnings) = parse_rc("bash", content, &env()).unwrap();
+        assert_eq!(1, warnings.len());
+        assert_eq!(2, config.data.len());
+        assert_eq!(
+            vec![("/opt/bin:/usr/bin".to_string(), true, "".to_string(), -1)],
+            entry(&config.data, "PATH")
+        );
+    }
+}
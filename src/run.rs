@@ -0,0 +1,133 @@
+pub mod run {
+    use std::path::Path;
+    use std::process::Command;
+
+    use crate::envvar::environment_variable::memory::MemoryEnvironment;
+    use crate::format::format;
+    use crate::json::config;
+    use crate::portable::portable::Translation;
+    use crate::settings::settings::Settings;
+
+    /// Variables kept from the current environment when starting from a clean one.
+    #[cfg(not(target_os = "windows"))]
+    pub const CLEAN_ENV_KEYS: [&str; 12] = [
+        "HOME",
+        "USER",
+        "LOGNAME",
+        "PATH",
+        "SHELL",
+        "TERM",
+        "LANG",
+        "TZ",
+        "TMPDIR",
+        "DISPLAY",
+        "WAYLAND_DISPLAY",
+        "XDG_RUNTIME_DIR",
+    ];
+
+    /// Variables kept from the current environment when starting from a clean one.
+    #[cfg(target_os = "windows")]
+    pub const CLEAN_ENV_KEYS: [&str; 12] = [
+        "PATH",
+        "PATHEXT",
+        "SYSTEMROOT",
+        "SYSTEMDRIVE",
+        "WINDIR",
+        "COMSPEC",
+        "TEMP",
+        "TMP",
+        "USERNAME",
+        "USERPROFILE",
+        "APPDATA",
+        "LOCALAPPDATA",
+    ];
+
+    fn is_kept(key: &str, keep: &[&str]) -> bool {
+        // names are case-insensitive on Windows, e.g., Path
+        let key = if cfg!(target_os = "windows") {
+            key.to_uppercase()
+        } else {
+            key.to_string()
+        };
+        let is_same = |k: &&str| {
+            if cfg!(target_os = "windows") {
+                k.eq_ignore_ascii_case(&key)
+            } else {
+                **k == key
+            }
+        };
+
+        key.starts_with("LC_") || CLEAN_ENV_KEYS.contains(&key.as_str()) || keep.iter().any(is_same)
+    }
+
+    /// Returns the variables of the current process that a clean environment keeps,
+    /// along with the ones named in `keep`.
+    pub fn clean_environment(keep: &[&str]) -> Vec<(String, String)> {
+        std::env::vars().filter(|(k, _)| is_kept(k, keep)).collect()
+    }
+
+    /// Computes the environment that results from applying the config at `filepath`
+    /// to the current (or a clean) environment.
+    pub fn build_environment(
+        filepath: &Path,
+        format: Option<&str>,
+        clean: bool,
+        keep: &[&str],
+        translation: &Translation,
+    ) -> Result<Vec<(String, String)>, String> {
+        let config = format::read_config_to_import(filepath, format, translation)?;
+        let mut envvar = if clean {
+            MemoryEnvironment::new(clean_environment(keep))
+        } else {
+            MemoryEnvironment::from_process()
+        };
+
+        config::apply_config(&config, false, false, &mut envvar)?;
+
+        Ok(envvar.vars().clone())
+    }
+
+    /// Runs the command after `--` with the config applied, exiting with its status.
+    pub fn exec(settings: &Settings) -> Result<(), String> {
+        let filepath = match settings.import {
+            Some(s) => s,
+            None => return Err("no config file specified".to_string()),
+        };
+
+        let command_line = if settings.trailing.is_empty() {
+            &settings.operands
+        } else {
+            &settings.trailing
+        };
+        let (program, args) = match command_line.split_first() {
+            Some(e) => e,
+            None => return Err("no command specified".to_string()),
+        };
+
+        let vars = build_environment(
+            Path::new(filepath),
+            settings.input_format,
+            settings.clean,
+            &settings.keep,
+            &Translation::from_settings(settings)?,
+        )?;
+
+        let mut command = Command::new(program);
+        command.args(args).env_clear().envs(vars);
+
+        // replace this process so that signals and the exit status are the command's own
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+
+            let e = command.exec();
+            Err(format!("{}: {}", e, program))
+        }
+
+        #[cfg(not(unix))]
+        match command.status() {
+            Ok(s) => std::process::exit(s.code().unwrap_or(1)),
+            Err(e) => Err(format!("{}: {}", e, program)),
+        }
+    }
+}
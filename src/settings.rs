@@ -3,6 +3,8 @@ pub mod settings {
     pub struct ShellRc<'a> {
         pub shell: Option<&'a str>,
         pub output_rc: Option<String>,
+        pub target: Option<&'a str>,
+        pub login: bool,
     }
 
     pub struct Settings<'a> {
@@ -12,15 +14,36 @@ pub mod settings {
         pub dry_run: bool,
         pub verbose: u32,
         pub version: bool,
+        pub command: Option<&'a str>,
+        pub operands: Vec<&'a str>,
+        pub trailing: Vec<&'a str>,
+        pub clean: bool,
+        pub keep: Vec<&'a str>,
+        pub filter: Vec<&'a str>,
         pub export: Option<&'a str>,
         pub import: Option<&'a str>,
+        pub format: Option<&'a str>,
+        pub input_format: Option<&'a str>,
+        pub oci: Option<&'a str>,
         pub set_variable: Option<(&'a str, &'a str)>,
         pub system: bool,
+        pub json: bool,
+        pub sync: bool,
+        pub interactive: bool,
+        pub translate: bool,
+        pub platform: Option<&'a str>,
+        pub mount_root: Option<&'a str>,
 
         #[cfg(target_os = "windows")]
         pub registry: bool,
 
         #[cfg(target_os = "linux")]
         pub shell_rc: ShellRc<'a>,
+
+        #[cfg(target_os = "linux")]
+        pub root: Option<&'a str>,
+
+        #[cfg(target_os = "linux")]
+        pub pid: Option<&'a str>,
     }
 }
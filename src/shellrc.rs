@@ -2,10 +2,263 @@ pub mod shellrc {
     use std::fs::File;
     use std::io::Write;
     use std::path::Path;
-    use std::rc::Rc;
 
-    use crate::envvar::environment_variable::EnvironmentVariable;
-    use crate::{envvar, utils};
+    use crate::envvar::environment_variable::env::PATH_DELIMITER;
+    use crate::utils;
+
+    /// Supported shells, as accepted by `--shell`.
+    pub const SHELLS: [&str; 12] = [
+        "bash",
+        "zsh",
+        "dash",
+        "ksh",
+        "mksh",
+        "fish",
+        "csh",
+        "tcsh",
+        "nushell",
+        "xonsh",
+        "elvish",
+        "powershell",
+    ];
+
+    /// Returns the name in [`SHELLS`] for `name`, accepting common aliases.
+    pub fn canonical_shell_name(name: &str) -> Option<&'static str> {
+        let name = match &*name.to_lowercase() {
+            "nu" => "nushell".to_string(),
+            "pwsh" | "ps" | "ps1" => "powershell".to_string(),
+            s => s.to_string(),
+        };
+
+        SHELLS.iter().find(|s| **s == name).copied()
+    }
+
+    /// Returns the default output path of the run control file for `shell`.
+    pub fn default_rc_path(shell: &str) -> String {
+        let config_home = utils::utils::config_home();
+        let filename = match canonical_shell_name(shell) {
+            // fish sources every file in conf.d at startup
+            Some("fish") => return format!("{}/fish/conf.d/envvar.fish", config_home),
+            // PowerShell refuses to dot-source a file without the .ps1 extension
+            Some("powershell") => "profile.ps1".to_string(),
+            Some("nushell") => "env.nu".to_string(),
+            Some(s) => format!("{}rc", s),
+            None => format!("{}rc", shell),
+        };
+
+        format!("{}/envvar/{}", config_home, filename)
+    }
+
+    /// Returns the command that loads the run control file at `path` into `shell`.
+    pub fn source_command(shell: &str, path: &str) -> Result<String, String> {
+        let result = match canonical_shell_name(shell) {
+            Some("bash" | "zsh" | "dash" | "ksh" | "mksh") => {
+                let path = quote_posix(path);
+                format!("if [ -f {} ]; then . {}; fi", path, path)
+            }
+            Some("csh" | "tcsh") => {
+                let path = quote_csh(path);
+                format!("if ( -f {} ) source {}", path, path)
+            }
+            Some("fish") => {
+                let path = quote_fish(path);
+                format!("test -f {}; and source {}", path, path)
+            }
+            Some("nushell") => format!("source {}", quote_nushell(path)),
+            Some("xonsh") => format!("source {}", quote_xonsh(path)),
+            Some("elvish") => format!("eval (slurp < {})", quote_elvish(path)),
+            Some("powershell") => {
+                let path = quote_powershell(path);
+                format!("if (Test-Path {}) {{ . {} }}", path, path)
+            }
+            _ => return Err(format!("unknown shell name: {}", shell)),
+        };
+
+        Ok(result)
+    }
+
+    /// Returns the command that writes the environment of `shell` to `path`, separated by NUL.
+    pub fn dump_env_command(shell: &str, path: &str) -> Result<String, String> {
+        let result = match canonical_shell_name(shell) {
+            Some("bash" | "zsh" | "dash" | "ksh" | "mksh") => {
+                format!("env -0 > {}", quote_posix(path))
+            }
+            Some("csh" | "tcsh") => format!("env -0 > {}", quote_csh(path)),
+            Some("fish") => format!("env -0 > {}", quote_fish(path)),
+            Some("nushell") => format!("^env -0 | save --raw -f {}", quote_nushell(path)),
+            Some("xonsh") => format!("env -0 > {}", quote_xonsh(path)),
+            Some("elvish") => format!("env -0 > {}", quote_elvish(path)),
+            // the output of a native command is decoded into lines when redirected
+            Some("powershell") => format!(
+                "Start-Process env -ArgumentList '-0' -RedirectStandardOutput {} -NoNewWindow -Wait",
+                quote_powershell(path)
+            ),
+            _ => return Err(format!("unknown shell name: {}", shell)),
+        };
+
+        Ok(result)
+    }
+
+    /// Returns the name of the executable of `shell`.
+    pub fn shell_program(shell: &str) -> String {
+        match canonical_shell_name(shell) {
+            Some("nushell") => "nu".to_string(),
+            Some("powershell") => "pwsh".to_string(),
+            Some(s) => s.to_string(),
+            None => shell.to_string(),
+        }
+    }
+
+    /// Returns the commands that prepend `marker` to the prompt of `shell`, if supported.
+    pub fn prompt_command(shell: &str, marker: &str) -> Option<String> {
+        let result = match canonical_shell_name(shell)? {
+            "bash" | "dash" | "ksh" | "mksh" => {
+                format!("PS1={}\"${{PS1-}}\"", quote_posix(marker))
+            }
+            "zsh" => format!("PROMPT={}\"${{PROMPT-}}\"", quote_posix(marker)),
+            "fish" => [
+                "functions -q fish_prompt; and functions -c fish_prompt __envvar_fish_prompt",
+                "function fish_prompt",
+                &format!("    echo -n {}", quote_fish(marker)),
+                "    functions -q __envvar_fish_prompt; and __envvar_fish_prompt",
+                "end",
+            ]
+            .join("\n"),
+            "powershell" => [
+                "$function:__envvar_prompt = $function:prompt",
+                "function global:prompt {",
+                &format!(
+                    "    {} + (& $function:__envvar_prompt)",
+                    quote_powershell(marker)
+                ),
+                "}",
+            ]
+            .join("\n"),
+            _ => return None,
+        };
+
+        Some(result)
+    }
+
+    /// Returns the prompt hook that runs `program hook-env` before every prompt of `shell`.
+    pub fn hook_script(shell: &str, program: &str) -> Result<String, String> {
+        let result = match canonical_shell_name(shell) {
+            Some("bash") => [
+                "_envvar_hook() {",
+                "    local previous_exit_status=$?",
+                &format!(
+                    "    eval \"$({} hook-env --shell=bash)\"",
+                    quote_posix(program)
+                ),
+                "    return $previous_exit_status",
+                "}",
+                "if [[ \";${PROMPT_COMMAND[*]:-};\" != *\";_envvar_hook;\"* ]]; then",
+                "    PROMPT_COMMAND=\"_envvar_hook${PROMPT_COMMAND:+;$PROMPT_COMMAND}\"",
+                "fi",
+            ]
+            .join("\n"),
+            Some("zsh") => [
+                "_envvar_hook() {",
+                &format!(
+                    "    eval \"$({} hook-env --shell=zsh)\"",
+                    quote_posix(program)
+                ),
+                "}",
+                "autoload -Uz add-zsh-hook",
+                "add-zsh-hook precmd _envvar_hook",
+                "add-zsh-hook chpwd _envvar_hook",
+            ]
+            .join("\n"),
+            Some("fish") => [
+                "function __envvar_hook --on-event fish_prompt --on-variable PWD",
+                &format!("    {} hook-env --shell=fish | source", quote_fish(program)),
+                "end",
+            ]
+            .join("\n"),
+            _ => return Err(format!("hook is not supported for {}", shell)),
+        };
+
+        Ok(result)
+    }
+
+    /// Variables whose name ends in `PATH` hold a list of paths, as fish does.
+    fn is_path_variable(name: &str) -> bool {
+        name.ends_with("PATH")
+    }
+
+    pub fn quote_posix(s: &str) -> String {
+        let is_safe = |c: char| c.is_ascii_alphanumeric() || "@%+=:,./-_".contains(c);
+        if !s.is_empty() && s.chars().all(is_safe) {
+            return s.to_string();
+        }
+
+        format!("'{}'", s.replace('\'', "'\\''"))
+    }
+
+    fn quote_csh(s: &str) -> String {
+        // history substitution and newlines are not disabled by single quotes
+        format!(
+            "'{}'",
+            s.replace('\'', "'\\''")
+                .replace('!', "\\!")
+                .replace('\n', "\\\n")
+        )
+    }
+
+    fn quote_fish(s: &str) -> String {
+        format!("'{}'", s.replace('\\', "\\\\").replace('\'', "\\'"))
+    }
+
+    fn quote_powershell(s: &str) -> String {
+        format!("'{}'", s.replace('\'', "''"))
+    }
+
+    fn quote_nushell(s: &str) -> String {
+        let mut result = String::new();
+        for c in s.chars() {
+            match c {
+                '\\' => result.push_str("\\\\"),
+                '"' => result.push_str("\\\""),
+                '\n' => result.push_str("\\n"),
+                '\r' => result.push_str("\\r"),
+                '\t' => result.push_str("\\t"),
+                _ => result.push(c),
+            }
+        }
+
+        format!("\"{}\"", result)
+    }
+
+    fn quote_xonsh(s: &str) -> String {
+        let mut result = String::new();
+        for c in s.chars() {
+            match c {
+                '\\' => result.push_str("\\\\"),
+                '\'' => result.push_str("\\'"),
+                '\n' => result.push_str("\\n"),
+                '\r' => result.push_str("\\r"),
+                _ => result.push(c),
+            }
+        }
+
+        format!("'{}'", result)
+    }
+
+    fn quote_elvish(s: &str) -> String {
+        format!("'{}'", s.replace('\'', "''"))
+    }
+
+    fn is_identifier(name: &str) -> bool {
+        name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    }
+
+    fn powershell_variable(name: &str) -> String {
+        if is_identifier(name) {
+            format!("$env:{}", name)
+        } else {
+            format!("${{env:{}}}", name.replace('}', "`}"))
+        }
+    }
 
     pub trait ShellRunCommandFile {
         fn new(shell: &str) -> Self;
@@ -16,6 +269,13 @@ pub mod shellrc {
                 Err(e) => return Err(e),
             };
 
+            if let Some(parent) = filepath.parent()
+                && !parent.as_os_str().is_empty()
+                && let Err(e) = std::fs::create_dir_all(parent)
+            {
+                return Err(e.to_string());
+            }
+
             let mut file = match File::create(filepath) {
                 Ok(f) => f,
                 Err(e) => return Err(e.to_string()),
@@ -29,43 +289,192 @@ pub mod shellrc {
 
         fn build(&self) -> Result<String, String>;
 
-        fn build_using_setenv(header: &String, list: &Vec<(String, String)>) -> String {
+        /// Builds `header` unless it is empty, then a line from `unset` for each removed name
+        /// and a line from `set` for each variable.
+        fn build_lines(
+            header: &str,
+            list: &[(String, String)],
+            removed: &[String],
+            unset: impl Fn(&str) -> String,
+            set: impl Fn(&str, &str) -> String,
+        ) -> String {
             let mut result: Vec<String> = Vec::new();
-            if header.len() > 0 {
+            if !header.is_empty() {
                 result.push(header.to_string());
             }
 
+            for k in removed.iter() {
+                result.push(unset(k));
+            }
+
             for (k, v) in list.iter() {
-                result.push(format!("setenv {} {}", k, v));
+                result.push(set(k, v));
             }
 
             result.join("\n")
         }
 
-        fn build_using_export(header: &String, list: &Vec<(String, String)>) -> String {
-            let mut result: Vec<String> = Vec::new();
-            if header.len() > 0 {
-                result.push(header.to_string());
-            }
+        fn build_using_setenv(
+            header: &str,
+            list: &[(String, String)],
+            removed: &[String],
+        ) -> String {
+            Self::build_lines(
+                header,
+                list,
+                removed,
+                |k| format!("unsetenv {}", k),
+                |k, v| format!("setenv {} {}", k, quote_csh(v)),
+            )
+        }
 
-            for (k, v) in list.iter() {
-                result.push(format!("export {}={}", k, v));
-            }
+        fn build_using_export(
+            header: &str,
+            list: &[(String, String)],
+            removed: &[String],
+        ) -> String {
+            Self::build_lines(
+                header,
+                list,
+                removed,
+                |k| format!("unset {}", k),
+                |k, v| format!("export {}={}", k, quote_posix(v)),
+            )
+        }
 
-            result.join("\n")
+        fn build_using_powershell(
+            header: &str,
+            list: &[(String, String)],
+            removed: &[String],
+        ) -> String {
+            Self::build_lines(
+                header,
+                list,
+                removed,
+                |k| {
+                    format!(
+                        "Remove-Item -LiteralPath {} -ErrorAction SilentlyContinue",
+                        quote_powershell(&format!("Env:{}", k))
+                    )
+                },
+                |k, v| {
+                    let value = if is_path_variable(k) {
+                        let values = v
+                            .split(PATH_DELIMITER)
+                            .map(quote_powershell)
+                            .collect::<Vec<String>>();
+                        format!("@({}) -join [IO.Path]::PathSeparator", values.join(", "))
+                    } else {
+                        quote_powershell(v)
+                    };
+                    format!("{} = {}", powershell_variable(k), value)
+                },
+            )
         }
 
-        fn build_using_powershell(header: &String, list: &Vec<(String, String)>) -> String {
-            let mut result: Vec<String> = Vec::new();
-            if header.len() > 0 {
-                result.push(header.to_string());
-            }
+        fn build_using_fish(header: &str, list: &[(String, String)], removed: &[String]) -> String {
+            Self::build_lines(
+                header,
+                list,
+                removed,
+                |k| format!("set -e {}", k),
+                |k, v| {
+                    let values = if is_path_variable(k) {
+                        v.split(PATH_DELIMITER)
+                            .map(quote_fish)
+                            .collect::<Vec<String>>()
+                    } else {
+                        vec![quote_fish(v)]
+                    };
+                    format!("set -gx {} {}", k, values.join(" "))
+                },
+            )
+        }
 
-            for (k, v) in list.iter() {
-                result.push(format!("export {}={}", k, v));
-            }
+        fn build_using_nushell(
+            header: &str,
+            list: &[(String, String)],
+            removed: &[String],
+        ) -> String {
+            Self::build_lines(
+                header,
+                list,
+                removed,
+                |k| format!("hide-env -i {}", quote_nushell(k)),
+                |k, v| {
+                    // only PATH is converted back to a string for external commands
+                    let value = if k == "PATH" {
+                        let values = v
+                            .split(PATH_DELIMITER)
+                            .map(quote_nushell)
+                            .collect::<Vec<String>>();
+                        format!("[{}]", values.join(" "))
+                    } else {
+                        quote_nushell(v)
+                    };
+                    let name = if is_identifier(k) {
+                        k.to_string()
+                    } else {
+                        quote_nushell(k)
+                    };
+                    format!("$env.{} = {}", name, value)
+                },
+            )
+        }
 
-            result.join("\n")
+        fn build_using_xonsh(
+            header: &str,
+            list: &[(String, String)],
+            removed: &[String],
+        ) -> String {
+            Self::build_lines(
+                header,
+                list,
+                removed,
+                |k| format!("${{...}}.pop({}, None)", quote_xonsh(k)),
+                |k, v| {
+                    let value = if is_path_variable(k) {
+                        let values = v
+                            .split(PATH_DELIMITER)
+                            .map(quote_xonsh)
+                            .collect::<Vec<String>>();
+                        format!("[{}]", values.join(", "))
+                    } else {
+                        quote_xonsh(v)
+                    };
+                    let name = if is_identifier(k) {
+                        format!("${}", k)
+                    } else {
+                        format!("${{{}}}", quote_xonsh(k))
+                    };
+                    format!("{} = {}", name, value)
+                },
+            )
+        }
+
+        fn build_using_elvish(
+            header: &str,
+            list: &[(String, String)],
+            removed: &[String],
+        ) -> String {
+            Self::build_lines(
+                header,
+                list,
+                removed,
+                |k| format!("unset-env {}", quote_elvish(k)),
+                |k, v| {
+                    // $paths is the list view of PATH
+                    if k == "PATH" {
+                        let values = v
+                            .split(PATH_DELIMITER)
+                            .map(quote_elvish)
+                            .collect::<Vec<String>>();
+                        return format!("set paths = [{}]", values.join(" "));
+                    }
+
+                    format!("set-env {} {}", quote_elvish(k), quote_elvish(v))
+                },
+            )
         }
 
         fn build_comment(shell: &str) -> String {
@@ -91,11 +500,18 @@ pub mod shellrc {
     pub struct ShellRunCommandFileData {
         shell: String,
         vars: Vec<(String, String)>,
+        removed: Vec<String>,
+        /// Values the changes apply to, read by `get` but not written
+        base: Vec<(String, String)>,
     }
 
     impl ShellRunCommandFileData {
         pub fn get(&self, key: &String) -> Result<String, String> {
-            for (k, v) in self.vars.iter() {
+            if self.removed.contains(key) {
+                return Err(format!("key not found: {}", key));
+            }
+
+            for (k, v) in self.vars.iter().chain(self.base.iter()) {
                 if k == key {
                     return Ok(v.to_string());
                 }
@@ -104,6 +520,15 @@ pub mod shellrc {
             Err(format!("key not found: {}", key))
         }
 
+        /// Records the value `key` has before any change, e.g., in the current environment.
+        /// Only the variables changed afterwards are written.
+        pub fn load(&mut self, key: &String, value: &String) {
+            match self.base.iter_mut().find(|(k, _)| k == key) {
+                Some(e) => e.1 = value.to_string(),
+                None => self.base.push((key.to_string(), value.to_string())),
+            }
+        }
+
         pub fn set(&mut self, key: &String, value: &String) {
             for (i, (k, _)) in self.vars.iter().enumerate() {
                 if k == key {
@@ -114,6 +539,8 @@ pub mod shellrc {
         }
 
         pub fn add(&mut self, key: &String, value: &String) {
+            self.removed.retain(|k| k != key);
+
             for (k, _) in self.vars.iter() {
                 if k == key {
                     self.set(key, value);
@@ -128,12 +555,46 @@ pub mod shellrc {
             for (i, (k, _)) in self.vars.iter().enumerate() {
                 if k == key {
                     self.vars.remove(i);
+                    self.removed.push(key.to_string());
                     return Ok(());
                 }
             }
 
+            if !self.removed.contains(key) && self.base.iter().any(|(k, _)| k == key) {
+                self.removed.push(key.to_string());
+                return Ok(());
+            }
+
             Err(format!("key not found: {}", key))
         }
+
+        /// Marks `key` to be removed whether or not it has been added.
+        pub fn unset(&mut self, key: &String) {
+            self.vars.retain(|(k, _)| k != key);
+            if !self.removed.contains(key) {
+                self.removed.push(key.to_string());
+            }
+        }
+
+        /// Builds the commands for the shell, starting with `header` unless it is empty.
+        pub fn build_with_header(&self, header: &str) -> Result<String, String> {
+            let result = match canonical_shell_name(&self.shell) {
+                Some("bash" | "zsh" | "dash" | "ksh" | "mksh") => {
+                    Self::build_using_export(header, &self.vars, &self.removed)
+                }
+                Some("fish") => Self::build_using_fish(header, &self.vars, &self.removed),
+                Some("csh" | "tcsh") => Self::build_using_setenv(header, &self.vars, &self.removed),
+                Some("nushell") => Self::build_using_nushell(header, &self.vars, &self.removed),
+                Some("xonsh") => Self::build_using_xonsh(header, &self.vars, &self.removed),
+                Some("elvish") => Self::build_using_elvish(header, &self.vars, &self.removed),
+                Some("powershell") => {
+                    Self::build_using_powershell(header, &self.vars, &self.removed)
+                }
+                _ => return Err(format!("unknown shell name: {}", self.shell)),
+            };
+
+            Ok(result)
+        }
     }
 
     impl ShellRunCommandFile for ShellRunCommandFileData {
@@ -141,32 +602,272 @@ pub mod shellrc {
             Self {
                 shell: shell.to_string(),
                 vars: Vec::new(),
+                removed: Vec::new(),
+                base: Vec::new(),
             }
         }
 
         fn build(&self) -> Result<String, String> {
-            let result = match &*self.shell.to_lowercase() {
-                "bash" | "zsh" | "fish" | "dash" => {
-                    let shell = self.shell.to_lowercase();
-                    let comment = Self::build_comment(&shell);
-                    Self::build_using_export(&comment, &self.vars)
-                }
-                "csh" => {
-                    let shell = self.shell.to_lowercase();
-                    let comment = Self::build_comment(&shell);
+            match canonical_shell_name(&self.shell) {
+                Some(shell) => self.build_with_header(&Self::build_comment(shell)),
+                None => Err(format!("unknown shell name: {}", self.shell)),
+            }
+        }
+    }
+}
 
-                    Self::build_using_setenv(&comment, &self.vars)
-                }
-                "powershell" | "ps" | "ps1" => {
-                    let shell = "powershell".to_string();
-                    let comment = Self::build_comment(&shell);
+pub mod detect {
+    use std::path::Path;
 
-                    Self::build_using_powershell(&comment, &self.vars)
-                }
-                _ => return Err(format!("unknown shell name: {}", self.shell)),
+    use crate::shellrc::shellrc::canonical_shell_name;
+
+    const DEFAULT_SHELL: &str = "bash";
+
+    fn shell_from_path(path: &Path) -> Option<&'static str> {
+        let name = path.file_name()?.to_string_lossy().to_string();
+        // login shells are started as "-bash"
+        if let Some(s) = canonical_shell_name(name.trim_start_matches('-')) {
+            return Some(s);
+        }
+
+        // e.g., /bin/sh is a link to dash
+        match std::fs::canonicalize(path) {
+            Ok(p) if p != path => shell_from_path(&p),
+            _ => None,
+        }
+    }
+
+    /// Detects the user's shell from `$SHELL`.
+    pub fn from_env() -> Option<&'static str> {
+        match std::env::var("SHELL") {
+            Ok(s) if !s.is_empty() => shell_from_path(Path::new(&s)),
+            _ => None,
+        }
+    }
+
+    /// Detects the shell that started this process.
+    pub fn from_parent_process() -> Option<&'static str> {
+        let ppid = std::os::unix::process::parent_id();
+        if let Ok(p) = std::fs::read_link(format!("/proc/{}/exe", ppid))
+            && let Some(s) = shell_from_path(&p)
+        {
+            return Some(s);
+        }
+
+        match std::fs::read_to_string(format!("/proc/{}/comm", ppid)) {
+            Ok(s) => shell_from_path(Path::new(s.trim())),
+            Err(_) => None,
+        }
+    }
+
+    /// Resolves the shell of the current session, which is the parent process when the
+    /// output is evaluated by the shell itself.
+    pub fn resolve_session_shell(
+        shell: Option<&str>,
+    ) -> Result<(&'static str, &'static str), String> {
+        if shell.is_none()
+            && let Some(s) = from_parent_process()
+        {
+            return Ok((s, "parent process"));
+        }
+
+        resolve_shell(shell)
+    }
+
+    /// Resolves the target shell and describes where it came from.
+    pub fn resolve_shell(shell: Option<&str>) -> Result<(&'static str, &'static str), String> {
+        if let Some(s) = shell {
+            return match canonical_shell_name(s) {
+                Some(s) => Ok((s, "--shell")),
+                None => Err(format!("unknown shell name: {}", s)),
             };
+        }
 
-            Ok(result)
+        if let Some(s) = from_env() {
+            return Ok((s, "$SHELL"));
+        }
+
+        if let Some(s) = from_parent_process() {
+            return Ok((s, "parent process"));
         }
+
+        Ok((DEFAULT_SHELL, "default"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shellrc::detect::resolve_shell;
+    use crate::shellrc::shellrc::{ShellRunCommandFile, ShellRunCommandFileData};
+
+    #[test]
+    fn test_build_fish_list() {
+        let mut data = ShellRunCommandFileData::new("fish");
+        data.add(&"PATH".to_string(), &"/usr/bin:/bin".to_string());
+        data.add(&"FOO".to_string(), &"a:b".to_string());
+
+        let build_result = data.build();
+        assert!(build_result.is_ok());
+
+        let lines = build_result.unwrap();
+        let lines = lines.lines().collect::<Vec<&str>>();
+        assert!(lines.contains(&"set -gx PATH '/usr/bin' '/bin'"));
+        assert!(lines.contains(&"set -gx FOO 'a:b'"));
+    }
+
+    #[test]
+    fn test_build_fish_quote_and_remove() {
+        let mut data = ShellRunCommandFileData::new("fish");
+        data.add(&"FOO".to_string(), &r"it's \".to_string());
+        data.add(&"BAR".to_string(), &"bar".to_string());
+        assert!(data.delete(&"BAR".to_string()).is_ok());
+
+        let build_result = data.build();
+        assert!(build_result.is_ok());
+
+        let lines = build_result.unwrap();
+        let lines = lines.lines().collect::<Vec<&str>>();
+        assert!(lines.contains(&r"set -gx FOO 'it\'s \\'"));
+        assert!(lines.contains(&"set -e BAR"));
+        assert!(!lines.contains(&"set -gx BAR 'bar'"));
+    }
+
+    #[test]
+    fn test_build_export_quote() {
+        let mut data = ShellRunCommandFileData::new("ksh");
+        data.add(&"PATH".to_string(), &"/usr/bin:/bin".to_string());
+        data.add(&"FOO".to_string(), &"it's a test".to_string());
+        data.add(&"EMPTY".to_string(), &"".to_string());
+
+        let build_result = data.build();
+        assert!(build_result.is_ok());
+
+        let lines = build_result.unwrap();
+        let lines = lines.lines().collect::<Vec<&str>>();
+        assert!(lines.contains(&"export PATH=/usr/bin:/bin"));
+        assert!(lines.contains(&r"export FOO='it'\''s a test'"));
+        assert!(lines.contains(&"export EMPTY=''"));
+    }
+
+    #[test]
+    fn test_build_tcsh_quote() {
+        let mut data = ShellRunCommandFileData::new("tcsh");
+        data.add(&"FOO".to_string(), &"wow!".to_string());
+        data.add(&"BAR".to_string(), &"bar".to_string());
+        assert!(data.delete(&"BAR".to_string()).is_ok());
+
+        let build_result = data.build();
+        assert!(build_result.is_ok());
+
+        let lines = build_result.unwrap();
+        let lines = lines.lines().collect::<Vec<&str>>();
+        assert!(lines.contains(&r"setenv FOO 'wow\!'"));
+        assert!(lines.contains(&"unsetenv BAR"));
+    }
+
+    #[test]
+    fn test_build_nushell() {
+        let mut data = ShellRunCommandFileData::new("nu");
+        data.add(&"PATH".to_string(), &"/usr/bin:/bin".to_string());
+        data.add(&"FOO".to_string(), &r#"say "hi""#.to_string());
+        data.add(&"BAR".to_string(), &"bar".to_string());
+        assert!(data.delete(&"BAR".to_string()).is_ok());
+
+        let build_result = data.build();
+        assert!(build_result.is_ok());
+
+        let lines = build_result.unwrap();
+        let lines = lines.lines().collect::<Vec<&str>>();
+        assert!(lines.contains(&r#"$env.PATH = ["/usr/bin" "/bin"]"#));
+        assert!(lines.contains(&r#"$env.FOO = "say \"hi\"""#));
+        assert!(lines.contains(&r#"hide-env -i "BAR""#));
+    }
+
+    #[test]
+    fn test_build_xonsh() {
+        let mut data = ShellRunCommandFileData::new("xonsh");
+        data.add(&"MANPATH".to_string(), &"/a:/b".to_string());
+        data.add(&"FOO".to_string(), &"it's".to_string());
+
+        let build_result = data.build();
+        assert!(build_result.is_ok());
+
+        let lines = build_result.unwrap();
+        let lines = lines.lines().collect::<Vec<&str>>();
+        assert!(lines.contains(&"$MANPATH = ['/a', '/b']"));
+        assert!(lines.contains(&r"$FOO = 'it\'s'"));
+    }
+
+    #[test]
+    fn test_build_elvish() {
+        let mut data = ShellRunCommandFileData::new("elvish");
+        data.add(&"PATH".to_string(), &"/usr/bin:/bin".to_string());
+        data.add(&"FOO".to_string(), &"it's".to_string());
+        data.add(&"BAR".to_string(), &"bar".to_string());
+        assert!(data.delete(&"BAR".to_string()).is_ok());
+
+        let build_result = data.build();
+        assert!(build_result.is_ok());
+
+        let lines = build_result.unwrap();
+        let lines = lines.lines().collect::<Vec<&str>>();
+        assert!(lines.contains(&"set paths = ['/usr/bin' '/bin']"));
+        assert!(lines.contains(&"set-env 'FOO' 'it''s'"));
+        assert!(lines.contains(&"unset-env 'BAR'"));
+    }
+
+    #[test]
+    fn test_resolve_shell() {
+        let r = resolve_shell(Some("pwsh"));
+        assert!(r.is_ok());
+        assert_eq!(("powershell", "--shell"), r.unwrap());
+
+        assert!(resolve_shell(Some("cmd")).is_err());
+    }
+
+    #[test]
+    fn test_build_changes_only() {
+        let mut data = ShellRunCommandFileData::new("bash");
+        data.load(&"TERM".to_string(), &"xterm".to_string());
+        data.load(&"OLD".to_string(), &"x".to_string());
+        data.load(&"PATH".to_string(), &"/usr/bin".to_string());
+        data.add(&"PATH".to_string(), &"/opt/bin:/usr/bin".to_string());
+        assert!(data.delete(&"OLD".to_string()).is_ok());
+        assert!(data.get(&"OLD".to_string()).is_err());
+        assert_eq!("xterm", data.get(&"TERM".to_string()).unwrap());
+
+        let lines = data.build().unwrap();
+        let lines = lines.lines().collect::<Vec<&str>>();
+        assert!(!lines.iter().any(|l| l.contains("TERM")));
+        assert!(lines.contains(&"export PATH=/opt/bin:/usr/bin"));
+        assert!(lines.contains(&"unset OLD"));
+    }
+
+    #[test]
+    fn test_build_unknown_shell() {
+        let data = ShellRunCommandFileData::new("cmd");
+        assert!(data.build().is_err());
+    }
+
+    #[test]
+    fn test_build_powershell() {
+        let mut data = ShellRunCommandFileData::new("pwsh");
+        data.add(&"PATH".to_string(), &"/usr/bin:/bin".to_string());
+        data.add(&"FOO".to_string(), &"it's".to_string());
+        data.add(&"BAR".to_string(), &"bar".to_string());
+        assert!(data.delete(&"BAR".to_string()).is_ok());
+
+        let build_result = data.build();
+        assert!(build_result.is_ok());
+
+        let lines = build_result.unwrap();
+        let lines = lines.lines().collect::<Vec<&str>>();
+        assert!(
+            lines.contains(&"$env:PATH = @('/usr/bin', '/bin') -join [IO.Path]::PathSeparator")
+        );
+        assert!(lines.contains(&"$env:FOO = 'it''s'"));
+        assert!(
+            lines.contains(&"Remove-Item -LiteralPath 'Env:BAR' -ErrorAction SilentlyContinue")
+        );
     }
 }
@@ -0,0 +1,402 @@
+pub mod snapshot {
+    use std::fs;
+    use std::io::Write;
+    use std::path::Path;
+
+    use serde::{Deserialize, Serialize};
+    use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
+
+    use crate::activate::activate::STACK_KEY;
+    use crate::diff::diff::IGNORE_KEYS;
+    use crate::envvar::environment_variable::EnvironmentVariable;
+    use crate::envvar::environment_variable::env::Environment;
+    use crate::format::format;
+    use crate::hook::hook::STATE_KEY;
+    use crate::json::config::{Config, ValueDetail};
+    use crate::settings::settings::Settings;
+    use crate::utils;
+
+    /// Subcommands of `snapshot`.
+    pub const SUBCOMMANDS: [&str; 5] = ["save", "list", "show", "restore", "delete"];
+
+    /// A copy of the environment at some point, with where it was taken.
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    pub struct Snapshot {
+        pub name: String,
+        /// RFC 3339 time in UTC
+        pub created: String,
+        pub host: String,
+        pub shell: String,
+        /// Version of envvar that took the snapshot
+        pub version: String,
+        pub vars: Vec<(String, String)>,
+    }
+
+    fn store_dir() -> String {
+        format!("{}/envvar/snapshots", utils::utils::data_home())
+    }
+
+    fn snapshot_path(name: &str) -> String {
+        format!("{}/{}.json", store_dir(), name)
+    }
+
+    /// Names become file names, so only letters, digits, `-`, `_` and `.` are accepted.
+    pub fn validate_name(name: &str) -> Result<(), String> {
+        let is_valid = !name.is_empty()
+            && !name.starts_with('.')
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c));
+        match is_valid {
+            true => Ok(()),
+            false => Err(format!(
+                "invalid snapshot name: {} (use letters, digits, '-', '_' and '.')",
+                name
+            )),
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    fn current_shell(settings: &Settings) -> String {
+        match crate::shellrc::detect::resolve_shell(settings.shell_rc.shell) {
+            Ok((shell, _)) => shell.to_string(),
+            Err(_) => "".to_string(),
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn current_shell(_settings: &Settings) -> String {
+        "".to_string()
+    }
+
+    pub fn read(name: &str) -> Result<Snapshot, String> {
+        validate_name(name)?;
+        let path = snapshot_path(name);
+        if !Path::new(&path).exists() {
+            return Err(format!("snapshot not found: {}", name));
+        }
+
+        let s = match fs::read_to_string(&path) {
+            Ok(s) => s,
+            Err(e) => return Err(format!("{}: {}", e, path)),
+        };
+        match serde_json::from_str::<Snapshot>(&s) {
+            Ok(s) => Ok(s),
+            Err(e) => Err(format!("{}: {}", e, path)),
+        }
+    }
+
+    /// Creates the store readable only by the user, as snapshots hold tokens and keys.
+    #[cfg(unix)]
+    fn create_store(dir: &str) -> std::io::Result<()> {
+        use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+
+        fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(dir)?;
+        fs::set_permissions(dir, fs::Permissions::from_mode(0o700))
+    }
+
+    #[cfg(not(unix))]
+    fn create_store(dir: &str) -> std::io::Result<()> {
+        fs::create_dir_all(dir)
+    }
+
+    #[cfg(unix)]
+    fn write_private(path: &str, content: &str) -> std::io::Result<()> {
+        use std::os::unix::fs::OpenOptionsExt;
+
+        fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(path)?
+            .write_all(content.as_bytes())
+    }
+
+    #[cfg(not(unix))]
+    fn write_private(path: &str, content: &str) -> std::io::Result<()> {
+        fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path)?
+            .write_all(content.as_bytes())
+    }
+
+    fn write(snapshot: &Snapshot) -> Result<(), String> {
+        let dir = store_dir();
+        if let Err(e) = create_store(&dir) {
+            return Err(format!("{}: {}", e, dir));
+        }
+
+        let path = snapshot_path(&snapshot.name);
+        let s = match serde_json::to_string_pretty(snapshot) {
+            Ok(s) => s,
+            Err(e) => return Err(e.to_string()),
+        };
+        match write_private(&path, &s) {
+            Ok(_) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => Err(format!(
+                "snapshot already exists: {} (delete it first)",
+                snapshot.name
+            )),
+            Err(e) => Err(format!("{}: {}", e, path)),
+        }
+    }
+
+    /// Reads all snapshots, oldest first. Files that cannot be read are skipped with a warning.
+    pub fn read_all() -> Result<Vec<Snapshot>, String> {
+        let dir = store_dir();
+        let entries = match fs::read_dir(&dir) {
+            Ok(e) => e,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(format!("{}: {}", e, dir)),
+        };
+
+        let mut result: Vec<Snapshot> = Vec::new();
+        for e in entries.flatten() {
+            let file_name = e.file_name().to_string_lossy().to_string();
+            let name = match file_name.strip_suffix(".json") {
+                Some(n) => n,
+                None => continue,
+            };
+            match read(name) {
+                Ok(s) => result.push(s),
+                Err(e) => eprintln!("warning: {}", e),
+            }
+        }
+        result.sort_by(|a, b| (&a.created, &a.name).cmp(&(&b.created, &b.name)));
+
+        Ok(result)
+    }
+
+    /// Variables owned by the shell or the session, which a restore leaves alone.
+    fn is_session_key(key: &str) -> bool {
+        IGNORE_KEYS.contains(&key) || key == STACK_KEY || key == STATE_KEY
+    }
+
+    /// Builds a config that sets every variable of `snapshot` when imported. Variables of
+    /// `current` missing from the snapshot cannot be removed by a config, so they are warned.
+    pub fn restore_config(
+        snapshot: &Snapshot,
+        current: &[(String, String)],
+    ) -> (Config, Vec<String>) {
+        let data = snapshot
+            .vars
+            .iter()
+            .filter(|(k, _)| !is_session_key(k))
+            .map(|(k, v)| ValueDetail {
+                key: k.to_string(),
+                value: v.to_string(),
+                overwrite: true,
+                delimiter: "".to_string(),
+                insert: -1,
+                secret: false,
+            })
+            .collect::<Vec<ValueDetail>>();
+
+        let warnings = current
+            .iter()
+            .filter(|(k, _)| !is_session_key(k))
+            .filter(|(k, _)| !snapshot.vars.iter().any(|(sk, _)| sk == k))
+            .map(|(k, _)| format!("{}: not in the snapshot, remove it by hand", k))
+            .collect::<Vec<String>>();
+
+        (
+            Config {
+                version: 1,
+                data,
+                metadata: None,
+            },
+            warnings,
+        )
+    }
+
+    fn save(settings: &Settings, name: Option<&str>) -> Result<(), String> {
+        let created = utils::utils::now();
+        // e.g., 2026-10-19T09:30:00Z becomes 20261019-093000
+        let name = match name {
+            Some(n) => n.to_string(),
+            None => created.replace(['-', ':', 'Z'], "").replace('T', "-"),
+        };
+        validate_name(&name)?;
+        if Path::new(&snapshot_path(&name)).exists() {
+            return Err(format!(
+                "snapshot already exists: {} (delete it first)",
+                name
+            ));
+        }
+
+        let snapshot = Snapshot {
+            name,
+            created,
+            host: utils::utils::hostname(),
+            shell: current_shell(settings),
+            version: utils::utils::get_version().to_string(),
+            vars: Environment::new().list()?,
+        };
+        if settings.dry_run {
+            println!(
+                "would save: {} ({} variables)",
+                snapshot.name,
+                snapshot.vars.len()
+            );
+            return Ok(());
+        }
+
+        write(&snapshot)?;
+        println!(
+            "saved: {} ({} variables)",
+            snapshot.name,
+            snapshot.vars.len()
+        );
+
+        Ok(())
+    }
+
+    fn list() -> Result<(), String> {
+        for s in read_all()?.iter() {
+            println!(
+                "{}\t{}\t{}\t{}\t{} variables",
+                s.name,
+                s.created,
+                s.host,
+                s.shell,
+                s.vars.len()
+            );
+        }
+
+        Ok(())
+    }
+
+    fn show(snapshot: &Snapshot, no_color: bool) -> std::io::Result<()> {
+        let choice = if no_color {
+            ColorChoice::Never
+        } else {
+            ColorChoice::Always
+        };
+        let mut stdout = StandardStream::stdout(choice);
+
+        writeln!(&mut stdout, "# name: {}", snapshot.name)?;
+        writeln!(&mut stdout, "# created: {}", snapshot.created)?;
+        writeln!(&mut stdout, "# host: {}", snapshot.host)?;
+        writeln!(&mut stdout, "# shell: {}", snapshot.shell)?;
+        writeln!(&mut stdout, "# version: {}", snapshot.version)?;
+        for (k, v) in snapshot.vars.iter() {
+            stdout.set_color(ColorSpec::new().set_fg(Some(Color::Cyan)))?;
+            write!(&mut stdout, "{}", k)?;
+            stdout.reset()?;
+            write!(&mut stdout, "=")?;
+            stdout.set_color(ColorSpec::new().set_fg(Some(Color::Yellow)))?;
+            writeln!(&mut stdout, "{}", v)?;
+        }
+        stdout.reset()
+    }
+
+    fn restore(settings: &Settings, snapshot: &Snapshot) -> Result<(), String> {
+        let (config, warnings) = restore_config(snapshot, &Environment::new().list()?);
+        for w in warnings.iter() {
+            eprintln!("warning: {}", w);
+        }
+
+        match settings.export {
+            Some(s) => format::write_config(&config, Path::new(s), settings.format),
+            None => format::print_config(&config, settings.format),
+        }
+    }
+
+    fn delete(settings: &Settings, name: &str) -> Result<(), String> {
+        read(name)?;
+        if settings.dry_run {
+            return Ok(());
+        }
+
+        let path = snapshot_path(name);
+        match fs::remove_file(&path) {
+            Ok(_) => Ok(()),
+            Err(e) => Err(format!("{}: {}", e, path)),
+        }
+    }
+
+    /// Runs `snapshot save [name]`, `list`, `show name`, `restore name` or `delete name`.
+    pub fn snapshot(settings: &Settings) -> Result<(), String> {
+        let name = settings.operands.get(1).copied();
+        let needs_name = || match name {
+            Some(n) => Ok(n),
+            None => Err("no snapshot name specified".to_string()),
+        };
+
+        match settings.operands.first().copied() {
+            Some("save") => save(settings, name),
+            Some("list") => list(),
+            Some("show") => match show(&read(needs_name()?)?, settings.no_color) {
+                Ok(_) => Ok(()),
+                Err(e) => Err(e.to_string()),
+            },
+            Some("restore") => restore(settings, &read(needs_name()?)?),
+            Some("delete") => delete(settings, needs_name()?),
+            Some(s) => Err(format!(
+                "unknown snapshot command: {} (supported: {})",
+                s,
+                SUBCOMMANDS.join(", ")
+            )),
+            None => Err(format!(
+                "no snapshot command specified (supported: {})",
+                SUBCOMMANDS.join(", ")
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::snapshot::snapshot::{Snapshot, restore_config, validate_name};
+    use crate::utils::utils::format_time;
+    use crate::utils::utils::vars;
+
+    #[test]
+    fn test_restore_config() {
+        let snapshot = Snapshot {
+            name: "good".to_string(),
+            created: format_time(1_760_866_200),
+            host: "host".to_string(),
+            shell: "bash".to_string(),
+            version: "0.1.0".to_string(),
+            vars: vars(&[
+                ("PATH", "/usr/bin:/bin"),
+                ("PWD", "/home"),
+                ("LANG", "C"),
+                ("ENVVAR_ACTIVATE", "[]"),
+            ]),
+        };
+        assert_eq!("2025-10-19T09:30:00Z", snapshot.created);
+
+        let current = vars(&[
+            ("PATH", "/opt/bin:/usr/bin:/bin"),
+            ("EXTRA", "x"),
+            ("SHLVL", "2"),
+        ]);
+        let (config, warnings) = restore_config(&snapshot, &current);
+        assert_eq!(
+            vec![("PATH", "/usr/bin:/bin", true), ("LANG", "C", true)],
+            config
+                .data
+                .iter()
+                .map(|e| (e.key.as_str(), e.value.as_str(), e.overwrite))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec!["EXTRA: not in the snapshot, remove it by hand"],
+            warnings
+        );
+    }
+
+    #[test]
+    fn test_validate_name() {
+        assert!(validate_name("known-good_1.0").is_ok());
+        assert!(validate_name("").is_err());
+        assert!(validate_name("../etc").is_err());
+        assert!(validate_name(".hidden").is_err());
+    }
+}
@@ -0,0 +1,219 @@
+pub mod source {
+    use std::fs;
+    use std::path::Path;
+
+    use crate::envvar::environment_variable::EnvironmentVariable;
+    use crate::envvar::environment_variable::env::{PATH, PATH_DELIMITER};
+    use crate::settings::settings::Settings;
+
+    /// Variables read from somewhere other than the current process, which cannot be changed.
+    pub struct ReadOnlyEnvironment {
+        origin: String,
+        vars: Vec<(String, String)>,
+    }
+
+    impl ReadOnlyEnvironment {
+        pub fn new(origin: &str, vars: Vec<(String, String)>) -> Self {
+            Self {
+                origin: origin.to_string(),
+                vars,
+            }
+        }
+
+        fn read_only(&self) -> Result<(), String> {
+            Err(format!("read-only: {}", self.origin))
+        }
+    }
+
+    impl EnvironmentVariable for ReadOnlyEnvironment {
+        fn list(&self) -> Result<Vec<(String, String)>, String> {
+            Ok(self.vars.clone())
+        }
+
+        fn get(&self, name: &String) -> Result<String, String> {
+            match self.vars.iter().find(|(k, _)| k == name) {
+                Some((_, v)) => Ok(v.to_string()),
+                None => Err(format!("environment variable not found: {}", name)),
+            }
+        }
+
+        fn set(&mut self, _name: &String, _value: &String) -> Result<(), String> {
+            self.read_only()
+        }
+
+        fn delete(&mut self, _name: &String) -> Result<(), String> {
+            self.read_only()
+        }
+
+        fn get_path(&self) -> Result<Vec<String>, String> {
+            self.get_list(&PATH.to_string(), &PATH_DELIMITER.to_string())
+        }
+
+        fn set_path(&mut self, _paths: &Vec<String>) -> Result<(), String> {
+            self.read_only()
+        }
+
+        fn append_path(&mut self, _path: &Path) -> Result<(), String> {
+            self.read_only()
+        }
+
+        fn insert_path(&mut self, _path: &Path, _to: usize) -> Result<(), String> {
+            self.read_only()
+        }
+
+        fn remove_path(&mut self, _by: usize) -> Result<(), String> {
+            self.read_only()
+        }
+
+        fn remove_path_from(&mut self, _path: &Path) -> Result<(), String> {
+            self.read_only()
+        }
+    }
+
+    /// Reads the environment a process started with from `/proc/<pid>/environ`.
+    /// Bytes that are not UTF-8 are replaced, with a warning.
+    #[cfg(target_os = "linux")]
+    pub fn from_pid(pid: &str) -> Result<ReadOnlyEnvironment, String> {
+        use crate::probe::probe::parse_environ;
+
+        if pid.is_empty() || !pid.chars().all(|c| c.is_ascii_digit()) {
+            return Err(format!("invalid process id: {}", pid));
+        }
+
+        let path = format!("/proc/{}/environ", pid);
+        let data = match fs::read(&path) {
+            Ok(d) => d,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Err(format!("no such process: {}", pid));
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+                return Err(format!(
+                    "permission denied: {} (the process belongs to another user)",
+                    path
+                ));
+            }
+            Err(e) => return Err(format!("{}: {}", e, path)),
+        };
+
+        for e in data.split(|b| *b == 0) {
+            if std::str::from_utf8(e).is_err() {
+                let s = String::from_utf8_lossy(e);
+                let key = s.split('=').next().unwrap_or_default();
+                eprintln!("warning: {}: not valid UTF-8, invalid bytes replaced", key);
+            }
+        }
+
+        Ok(ReadOnlyEnvironment::new(&path, parse_environ(&data)))
+    }
+
+    /// Returns the `Env` array of an OCI image config, a runtime `config.json`
+    /// or the output of `docker inspect`, adding what could not be read to `warnings`.
+    pub fn parse_oci(
+        json: &str,
+        warnings: &mut Vec<String>,
+    ) -> Result<Vec<(String, String)>, String> {
+        let document = match serde_json::from_str::<serde_json::Value>(json) {
+            Ok(v) => v,
+            Err(e) => return Err(e.to_string()),
+        };
+        // `docker inspect` prints an array of objects
+        let document = match document.as_array() {
+            Some(a) if a.len() == 1 => &a[0],
+            Some(_) => return Err("expected a single image or container".to_string()),
+            None => &document,
+        };
+
+        let env = ["/config/Env", "/Config/Env", "/process/env"]
+            .iter()
+            .find_map(|p| document.pointer(p))
+            .and_then(|v| v.as_array());
+        let env = match env {
+            Some(e) => e,
+            None => return Err("no Env found in the config".to_string()),
+        };
+
+        let mut vars: Vec<(String, String)> = Vec::new();
+        for e in env.iter() {
+            match e.as_str().and_then(|s| s.split_once('=')) {
+                Some((k, v)) => {
+                    vars.retain(|(vk, _)| vk != k);
+                    vars.push((k.to_string(), v.to_string()));
+                }
+                None => warnings.push(format!("{}: expected KEY=VALUE, skipped", e)),
+            }
+        }
+
+        Ok(vars)
+    }
+
+    pub fn from_oci(path: &str) -> Result<ReadOnlyEnvironment, String> {
+        let json = match fs::read_to_string(path) {
+            Ok(s) => s,
+            Err(e) => return Err(format!("{}: {}", e, path)),
+        };
+
+        let mut warnings: Vec<String> = Vec::new();
+        let vars = parse_oci(&json, &mut warnings)?;
+        for w in warnings.iter() {
+            eprintln!("warning: {}", w);
+        }
+
+        Ok(ReadOnlyEnvironment::new(path, vars))
+    }
+
+    /// Opens the source given with --pid or --oci, or returns `None` for the current process.
+    pub fn open(settings: &Settings) -> Result<Option<ReadOnlyEnvironment>, String> {
+        #[cfg(target_os = "linux")]
+        if let Some(pid) = settings.pid {
+            return from_pid(pid).map(Some);
+        }
+
+        match settings.oci {
+            Some(path) => from_oci(path).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::envvar::environment_variable::EnvironmentVariable;
+    use crate::source::source::parse_oci;
+
+    #[test]
+    fn test_parse_oci() {
+        let image = r#"{"architecture":"amd64","config":{"Env":["PATH=/usr/bin:/bin","EMPTY=","A=x=y","BROKEN"]}}"#;
+        let mut warnings: Vec<String> = Vec::new();
+        let vars = parse_oci(image, &mut warnings).unwrap();
+        assert_eq!(
+            vec![
+                ("PATH".to_string(), "/usr/bin:/bin".to_string()),
+                ("EMPTY".to_string(), "".to_string()),
+                ("A".to_string(), "x=y".to_string()),
+            ],
+            vars
+        );
+        assert_eq!(1, warnings.len());
+
+        let runtime = r#"{"ociVersion":"1.0.2","process":{"env":["TERM=xterm"]}}"#;
+        assert_eq!(1, parse_oci(runtime, &mut warnings).unwrap().len());
+
+        let inspect = r#"[{"Id":"abc","Config":{"Env":["HOME=/root"]}}]"#;
+        assert_eq!(1, parse_oci(inspect, &mut warnings).unwrap().len());
+
+        assert!(parse_oci(r#"{"config":{}}"#, &mut warnings).is_err());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_from_pid() {
+        use crate::source::source::from_pid;
+
+        let mut env = from_pid(&std::process::id().to_string()).unwrap();
+        let path = env.get(&"PATH".to_string());
+        assert_eq!(std::env::var("PATH").ok(), path.ok());
+        assert!(env.set(&"A".to_string(), &"B".to_string()).is_err());
+
+        assert!(from_pid("not-a-pid").is_err());
+    }
+}
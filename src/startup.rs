@@ -0,0 +1,337 @@
+pub mod startup {
+    use std::fs;
+    use std::path::Path;
+
+    use crate::rcparse::rcparse::parse_rc;
+    use crate::settings::settings::Settings;
+    use crate::shellrc::detect::resolve_shell;
+    use crate::shellrc::shellrc::{default_rc_path, source_command};
+    use crate::utils;
+
+    const BEGIN_MARKER: &str = "# >>> envvar managed block >>>";
+    const END_MARKER: &str = "# <<< envvar managed block <<<";
+
+    /// Variables that belong to a session, stale when loaded by another one.
+    const SESSION_KEYS: [&str; 11] = [
+        "TERM",
+        "OLDPWD",
+        "DISPLAY",
+        "WAYLAND_DISPLAY",
+        "SSH_AUTH_SOCK",
+        "SSH_AGENT_PID",
+        "SSH_CONNECTION",
+        "SSH_TTY",
+        "TMUX",
+        "XDG_SESSION_ID",
+        "DBUS_SESSION_BUS_ADDRESS",
+    ];
+
+    fn home_file(name: &str) -> String {
+        utils::utils::expand_tilde(&format!("~/{}", name))
+    }
+
+    fn first_existing(candidates: &[String]) -> String {
+        match candidates.iter().find(|p| Path::new(p).exists()) {
+            Some(p) => p.to_string(),
+            None => candidates[0].to_string(),
+        }
+    }
+
+    /// Returns the directory zsh reads its startup files from.
+    pub fn zdotdir() -> String {
+        match std::env::var("ZDOTDIR") {
+            Ok(s) if !s.is_empty() => s,
+            _ => utils::utils::expand_tilde("~"),
+        }
+    }
+
+    /// Returns the startup file that `shell` reads for login or interactive sessions.
+    pub fn startup_file(shell: &str, login: bool) -> Result<String, String> {
+        let config_home = utils::utils::config_home();
+        let zdotdir = zdotdir();
+        // interactive POSIX shells only read the file named by $ENV
+        let env_file = match std::env::var("ENV") {
+            Ok(s) if !s.is_empty() => Some(utils::utils::expand_tilde(&s)),
+            _ => None,
+        };
+
+        let result = match (shell, login) {
+            // bash reads only the first of these that exists
+            ("bash", true) => first_existing(&[
+                home_file(".bash_profile"),
+                home_file(".bash_login"),
+                home_file(".profile"),
+            ]),
+            ("bash", false) => home_file(".bashrc"),
+            ("zsh", true) => format!("{}/.zprofile", zdotdir),
+            ("zsh", false) => format!("{}/.zshrc", zdotdir),
+            ("ksh", false) => env_file.unwrap_or(home_file(".kshrc")),
+            ("mksh", false) => env_file.unwrap_or(home_file(".mkshrc")),
+            ("dash", false) => env_file.unwrap_or(home_file(".profile")),
+            ("ksh" | "mksh" | "dash", true) => home_file(".profile"),
+            ("csh", false) => home_file(".cshrc"),
+            // tcsh falls back to .cshrc when .tcshrc does not exist
+            ("tcsh", false) => first_existing(&[home_file(".tcshrc"), home_file(".cshrc")]),
+            ("csh" | "tcsh", true) => home_file(".login"),
+            ("fish", _) => format!("{}/fish/config.fish", config_home),
+            ("nushell", true) => format!("{}/nushell/login.nu", config_home),
+            ("nushell", false) => format!("{}/nushell/env.nu", config_home),
+            ("xonsh", _) => home_file(".xonshrc"),
+            ("elvish", _) => format!("{}/elvish/rc.elv", config_home),
+            ("powershell", _) => format!(
+                "{}/powershell/Microsoft.PowerShell_profile.ps1",
+                config_home
+            ),
+            _ => return Err(format!("unknown shell name: {}", shell)),
+        };
+
+        Ok(result)
+    }
+
+    /// Builds the managed block that loads the run control file at `rc_path`.
+    pub fn build_block(shell: &str, rc_path: &str, login: bool) -> Result<Vec<String>, String> {
+        let command = source_command(shell, rc_path)?;
+
+        let mut result: Vec<String> = Vec::new();
+        result.push(BEGIN_MARKER.to_string());
+        result.push("# Managed by envvar. Run `envvar uninstall` to remove.".to_string());
+        // fish reads config.fish for every session
+        if shell == "fish" && login {
+            result.push("if status is-login".to_string());
+            result.push(format!("    {}", command));
+            result.push("end".to_string());
+        } else {
+            result.push(command);
+        }
+        result.push(END_MARKER.to_string());
+
+        Ok(result)
+    }
+
+    fn find_block(lines: &[&str]) -> Result<Option<(usize, usize)>, String> {
+        let begin = match lines.iter().position(|l| l.trim_end() == BEGIN_MARKER) {
+            Some(i) => i,
+            None => return Ok(None),
+        };
+
+        match lines[begin..]
+            .iter()
+            .position(|l| l.trim_end() == END_MARKER)
+        {
+            Some(i) => Ok(Some((begin, begin + i))),
+            None => Err(format!("unterminated managed block: {}", BEGIN_MARKER)),
+        }
+    }
+
+    /// Replaces, appends or (if `block` is `None`) removes the managed block in `content`,
+    /// keeping its line endings.
+    pub fn update_block(content: &str, block: Option<&Vec<String>>) -> Result<String, String> {
+        let newline = if content.contains("\r\n") {
+            "\r\n"
+        } else {
+            "\n"
+        };
+        let lines = content.lines().collect::<Vec<&str>>();
+        let mut result: Vec<String> = Vec::new();
+
+        match find_block(&lines)? {
+            Some((begin, end)) => {
+                result.extend(lines[..begin].iter().map(|l| l.to_string()));
+                match block {
+                    Some(b) => result.extend(b.iter().cloned()),
+                    // drop the separator added on install
+                    None => {
+                        if result.last().is_some_and(|l| l.is_empty()) {
+                            result.pop();
+                        }
+                    }
+                }
+                result.extend(lines[end + 1..].iter().map(|l| l.to_string()));
+            }
+            None => {
+                result.extend(lines.iter().map(|l| l.to_string()));
+                if let Some(b) = block {
+                    if result.last().is_some_and(|l| !l.is_empty()) {
+                        result.push(String::new());
+                    }
+                    result.extend(b.iter().cloned());
+                }
+            }
+        }
+
+        let mut s = result.join(newline);
+        if !s.is_empty() {
+            s.push_str(newline);
+        }
+
+        Ok(s)
+    }
+
+    fn read_startup_file(path: &str) -> Result<String, String> {
+        if !Path::new(path).exists() {
+            return Ok(String::new());
+        }
+
+        match fs::read_to_string(path) {
+            Ok(s) => Ok(s),
+            Err(e) => Err(format!("{}: {}", e, path)),
+        }
+    }
+
+    fn write_startup_file(
+        settings: &Settings,
+        path: &str,
+        old: &str,
+        new: &str,
+    ) -> Result<(), String> {
+        if let Err(e) = utils::utils::print_diff(path, old, new, settings.no_color) {
+            return Err(e.to_string());
+        }
+
+        if settings.dry_run {
+            return Ok(());
+        }
+
+        if let Some(parent) = Path::new(path).parent()
+            && let Err(e) = fs::create_dir_all(parent)
+        {
+            return Err(e.to_string());
+        }
+
+        match fs::write(path, new) {
+            Ok(_) => Ok(()),
+            Err(e) => Err(format!("{}: {}", e, path)),
+        }
+    }
+
+    /// Lists the session variables that the rc file `content` sets. An rc file written by an
+    /// older envvar holds the whole environment, which every new shell would load.
+    pub fn session_keys(shell: &str, content: &str) -> Vec<String> {
+        match parse_rc(shell, content, &Vec::new()) {
+            Ok((config, _)) => config
+                .data
+                .into_iter()
+                .map(|e| e.key)
+                .filter(|k| SESSION_KEYS.contains(&k.as_str()))
+                .collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// Inserts the managed block into the startup file of the target shell.
+    pub fn install(settings: &Settings) -> Result<(), String> {
+        let (shell, _) = resolve_shell(settings.shell_rc.shell)?;
+        let rc_path = match &settings.shell_rc.output_rc {
+            Some(s) => s.to_string(),
+            None => default_rc_path(shell),
+        };
+        let rc_path = match std::path::absolute(&rc_path) {
+            Ok(p) => p.to_string_lossy().to_string(),
+            Err(e) => return Err(e.to_string()),
+        };
+
+        if let Ok(content) = fs::read_to_string(&rc_path) {
+            let keys = session_keys(shell, &content);
+            if !keys.is_empty() {
+                eprintln!(
+                    "warning: {} sets {}, which belong to the session, import again to rewrite it",
+                    rc_path,
+                    keys.join(", ")
+                );
+            }
+        }
+
+        let conf_d = format!("{}/fish/conf.d/", utils::utils::config_home());
+        if shell == "fish" && rc_path.starts_with(&conf_d) {
+            println!("fish loads {} automatically, nothing to install.", rc_path);
+            return Ok(());
+        }
+
+        let path = startup_file(shell, settings.shell_rc.login)?;
+        let old = read_startup_file(&path)?;
+        let block = build_block(shell, &rc_path, settings.shell_rc.login)?;
+        let new = update_block(&old, Some(&block))?;
+
+        if old == new {
+            println!("already installed: {}", path);
+            return Ok(());
+        }
+
+        write_startup_file(settings, &path, &old, &new)?;
+        if !settings.dry_run {
+            println!("installed: {}", path);
+        }
+
+        Ok(())
+    }
+
+    /// Removes the managed block from the startup file of the target shell.
+    pub fn uninstall(settings: &Settings) -> Result<(), String> {
+        let (shell, _) = resolve_shell(settings.shell_rc.shell)?;
+        let path = startup_file(shell, settings.shell_rc.login)?;
+        let old = read_startup_file(&path)?;
+        let new = update_block(&old, None)?;
+
+        if old == new {
+            println!("not installed: {}", path);
+            return Ok(());
+        }
+
+        write_startup_file(settings, &path, &old, &new)?;
+        if !settings.dry_run {
+            println!("uninstalled: {}", path);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::startup::startup::{build_block, session_keys, update_block};
+
+    #[test]
+    fn test_update_block_idempotent() {
+        let block_result = build_block("bash", "/home/user/.config/envvar/bashrc", false);
+        assert!(block_result.is_ok());
+        let block = block_result.unwrap();
+
+        let content = "alias ll='ls -l'\n";
+        let installed = update_block(content, Some(&block)).unwrap();
+        assert!(installed.starts_with("alias ll='ls -l'\n\n# >>> envvar managed block >>>\n"));
+        assert!(installed.contains(
+            "if [ -f /home/user/.config/envvar/bashrc ]; then . /home/user/.config/envvar/bashrc; fi\n"
+        ));
+
+        let reinstalled = update_block(&installed, Some(&block)).unwrap();
+        assert_eq!(installed, reinstalled);
+
+        let uninstalled = update_block(&installed, None).unwrap();
+        assert_eq!(content, uninstalled);
+    }
+
+    #[test]
+    fn test_update_block_crlf() {
+        let block = build_block("bash", "/home/user/.config/envvar/bashrc", false).unwrap();
+        let content = "alias ll='ls -l'\r\n";
+        let installed = update_block(content, Some(&block)).unwrap();
+        assert!(!installed.replace("\r\n", "").contains('\n'));
+
+        let uninstalled = update_block(&installed, None).unwrap();
+        assert_eq!(content, uninstalled);
+    }
+
+    #[test]
+    fn test_update_block_unterminated() {
+        let content = "# >>> envvar managed block >>>\nsource foo\n";
+        assert!(update_block(content, None).is_err());
+    }
+
+    #[test]
+    fn test_session_keys() {
+        let content =
+            "export TERM=xterm-256color\nexport APP=1\nexport SSH_AUTH_SOCK=/tmp/ssh-x/agent.1\n";
+        assert_eq!(vec!["TERM", "SSH_AUTH_SOCK"], session_keys("bash", content));
+        assert!(session_keys("fish", "set -gx APP '1'\n").is_empty());
+    }
+}
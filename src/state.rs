@@ -0,0 +1,308 @@
+pub mod state {
+    //! The variables and list elements set by `--import`, so that `--sync` can remove the ones
+    //! no longer in the config without touching the others.
+
+    use std::fs;
+    use std::path::Path;
+
+    use serde::{Deserialize, Serialize};
+
+    use crate::envvar::environment_variable::EnvironmentVariable;
+    use crate::format::format;
+    use crate::json::config::{self, Config};
+    use crate::portable::portable::Translation;
+    use crate::utils;
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    pub struct Element {
+        pub key: String,
+        pub delimiter: String,
+        pub value: String,
+    }
+
+    /// What envvar has set in `target`, e.g., the path of the rc file.
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+    pub struct Managed {
+        pub target: String,
+        pub vars: Vec<String>,
+        pub elements: Vec<Element>,
+    }
+
+    fn store_path() -> String {
+        format!("{}/envvar/state.json", utils::utils::data_home())
+    }
+
+    fn read_store() -> Result<Vec<Managed>, String> {
+        let path = store_path();
+        if !Path::new(&path).exists() {
+            return Ok(Vec::new());
+        }
+
+        let s = match fs::read_to_string(&path) {
+            Ok(s) => s,
+            Err(e) => return Err(format!("{}: {}", e, path)),
+        };
+        match serde_json::from_str::<Vec<Managed>>(&s) {
+            Ok(m) => Ok(m),
+            Err(e) => Err(format!("{}: {}", e, path)),
+        }
+    }
+
+    fn write_store(entries: &Vec<Managed>) -> Result<(), String> {
+        let path = store_path();
+        if let Some(parent) = Path::new(&path).parent()
+            && let Err(e) = fs::create_dir_all(parent)
+        {
+            return Err(e.to_string());
+        }
+
+        let s = match serde_json::to_string_pretty(entries) {
+            Ok(s) => s,
+            Err(e) => return Err(e.to_string()),
+        };
+        match fs::write(&path, s) {
+            Ok(_) => Ok(()),
+            Err(e) => Err(format!("{}: {}", e, path)),
+        }
+    }
+
+    /// Lists what `config` sets, leaving out what was in `before` unless `previous` has it,
+    /// as it was set by someone else. An extension of a list is tracked element by element.
+    pub fn track(
+        target: &str,
+        config: &Config,
+        before: &[(String, String)],
+        previous: &Managed,
+    ) -> Managed {
+        let mut result = Managed {
+            target: target.to_string(),
+            ..Default::default()
+        };
+
+        for e in config.data.iter() {
+            let prior = before.iter().find(|(k, _)| *k == e.key).map(|(_, v)| v);
+
+            if e.overwrite || e.delimiter.is_empty() {
+                if (prior.is_none() || previous.vars.contains(&e.key))
+                    && !result.vars.contains(&e.key)
+                {
+                    result.vars.push(e.key.to_string());
+                }
+                continue;
+            }
+
+            for value in e.value.split(e.delimiter.as_str()) {
+                let element = Element {
+                    key: e.key.to_string(),
+                    delimiter: e.delimiter.to_string(),
+                    value: value.to_string(),
+                };
+                let is_listed =
+                    prior.is_some_and(|p| p.split(e.delimiter.as_str()).any(|v| v == value));
+                if (!is_listed || previous.elements.contains(&element))
+                    && !result.elements.contains(&element)
+                {
+                    result.elements.push(element);
+                }
+            }
+        }
+
+        result
+    }
+
+    /// Returns what `previous` has but `current` does not.
+    pub fn stale(previous: &Managed, current: &Managed) -> Managed {
+        Managed {
+            target: previous.target.to_string(),
+            vars: previous
+                .vars
+                .iter()
+                .filter(|k| !current.vars.contains(k))
+                .filter(|k| !current.elements.iter().any(|e| e.key == **k))
+                .cloned()
+                .collect(),
+            elements: previous
+                .elements
+                .iter()
+                .filter(|e| !current.elements.contains(e))
+                .filter(|e| !current.vars.contains(&e.key))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    fn union(a: &Managed, b: &Managed) -> Managed {
+        let mut result = a.clone();
+        for k in b.vars.iter() {
+            if !result.vars.contains(k) {
+                result.vars.push(k.to_string());
+            }
+        }
+        for e in b.elements.iter() {
+            if !result.elements.contains(e) {
+                result.elements.push(e.clone());
+            }
+        }
+
+        result
+    }
+
+    /// Sets what `previous` manages to its current value again, so that a target holding only
+    /// the changes, such as the rc file, keeps what earlier imports set.
+    fn keep<T: EnvironmentVariable>(previous: &Managed, envvar: &mut T) -> Result<(), String> {
+        let keys = previous
+            .vars
+            .iter()
+            .chain(previous.elements.iter().map(|e| &e.key));
+        for k in keys {
+            if let Ok(v) = envvar.get(k) {
+                envvar.set(k, &v)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Removes the variables and elements of `stale` that are still in `envvar`.
+    fn prune<T: EnvironmentVariable>(
+        stale: &Managed,
+        dry_run: bool,
+        envvar: &mut T,
+    ) -> Result<(), String> {
+        println!();
+        println!("remove:");
+        for k in stale.vars.iter() {
+            if envvar.get(k).is_err() {
+                continue;
+            }
+            println!("        {}", k);
+            if !dry_run {
+                envvar.delete(k)?;
+            }
+        }
+        for e in stale.elements.iter() {
+            let list = match envvar.get_list(&e.key, &e.delimiter) {
+                Ok(l) => l,
+                Err(_) => continue,
+            };
+            if !list.contains(&e.value) {
+                continue;
+            }
+            println!("        {}", e.key);
+            println!("          element: {}", e.value);
+            if !dry_run {
+                envvar.remove_list_from(&e.key, &e.value, &e.delimiter)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Imports the config at `filepath` into `envvar` and returns what is set for `target`,
+    /// to be passed to `record` once the target is written, or `None` with `dry_run`.
+    /// With `sync`, what an earlier import set but the config no longer has is removed.
+    pub fn import_envvar<T: EnvironmentVariable>(
+        filepath: &Path,
+        format: Option<&str>,
+        target: &str,
+        dry_run: bool,
+        sync: bool,
+        translation: &Translation,
+        envvar: &mut T,
+    ) -> Result<Option<Managed>, String> {
+        let config = format::read_config_to_import(filepath, format, translation)?;
+        let before = envvar.list()?;
+
+        let store = read_store()?;
+        let previous = match store.iter().find(|m| m.target == target) {
+            Some(m) => m.clone(),
+            None => Managed {
+                target: target.to_string(),
+                ..Default::default()
+            },
+        };
+        let current = track(target, &config, &before, &previous);
+
+        if !dry_run {
+            keep(&previous, envvar)?;
+        }
+        config::apply_config(&config, dry_run, true, envvar)?;
+
+        let managed = match sync {
+            true => {
+                prune(&stale(&previous, &current), dry_run, envvar)?;
+                current
+            }
+            false => union(&previous, &current),
+        };
+
+        if dry_run {
+            return Ok(None);
+        }
+        Ok(Some(managed))
+    }
+
+    /// Saves what an import set for its target, replacing the earlier record.
+    pub fn record(managed: Managed) -> Result<(), String> {
+        let mut store = read_store()?;
+        store.retain(|m| m.target != managed.target);
+        store.push(managed);
+        write_store(&store)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::json::config::Config;
+    use crate::state::state::{Element, Managed, stale, track};
+    use crate::utils::utils::vars;
+
+    fn element(key: &str, value: &str) -> Element {
+        Element {
+            key: key.to_string(),
+            delimiter: ":".to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_track() {
+        let json = r#"{"version":1,"data":[
+            {"key":"APP","value":"1"},
+            {"key":"LANG","value":"C","overwrite":true},
+            {"key":"PATH","value":"/opt/bin:/usr/bin","delimiter":":","insert":0}
+        ]}"#;
+        let config: Config = serde_json::from_str(json).unwrap();
+        let before = vars(&[("LANG", "en_US.UTF-8"), ("PATH", "/usr/bin:/bin")]);
+
+        let first = track("rc", &config, &before, &Managed::default());
+        assert_eq!(vec!["APP"], first.vars);
+        assert_eq!(vec![element("PATH", "/opt/bin")], first.elements);
+
+        // the second import sees what the first one set
+        let before = vars(&[
+            ("APP", "1"),
+            ("LANG", "C"),
+            ("PATH", "/opt/bin:/usr/bin:/bin"),
+        ]);
+        assert_eq!(first, track("rc", &config, &before, &first));
+    }
+
+    #[test]
+    fn test_stale() {
+        let previous = Managed {
+            target: "rc".to_string(),
+            vars: vec!["APP".to_string(), "OLD".to_string()],
+            elements: vec![element("PATH", "/opt/bin"), element("PATH", "/opt/old")],
+        };
+        let current = Managed {
+            target: "rc".to_string(),
+            vars: vec!["APP".to_string()],
+            elements: vec![element("PATH", "/opt/bin")],
+        };
+
+        let result = stale(&previous, &current);
+        assert_eq!(vec!["OLD"], result.vars);
+        assert_eq!(vec![element("PATH", "/opt/old")], result.elements);
+    }
+}
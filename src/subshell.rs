@@ -0,0 +1,195 @@
+pub mod subshell {
+    use std::fs;
+    use std::path::Path;
+    use std::process::Command;
+
+    use crate::activate::activate::record_changes;
+    use crate::portable::portable::Translation;
+    use crate::run::run::build_environment;
+    use crate::settings::settings::Settings;
+    use crate::shellrc::detect::resolve_shell;
+    use crate::shellrc::shellrc::{
+        ShellRunCommandFile, ShellRunCommandFileData, prompt_command, shell_program, source_command,
+    };
+    use crate::startup::startup::{startup_file, zdotdir};
+    use crate::utils;
+
+    /// Names the profile that is active in the subshell.
+    pub const PROFILE_KEY: &str = "ENVVAR_PROFILE";
+
+    /// Builds the startup script of the subshell. It loads `user_rc` first, so that the
+    /// config and the prompt marker are applied on top of the user's own settings.
+    pub fn build_init(
+        shell: &str,
+        user_rc: Option<&str>,
+        before: &[(String, String)],
+        after: &[(String, String)],
+        profile: &str,
+    ) -> Result<String, String> {
+        let mut lines: Vec<String> = Vec::new();
+        if let Some(rc) = user_rc {
+            lines.push(source_command(shell, rc)?);
+        }
+
+        let mut data = ShellRunCommandFileData::new(shell);
+        record_changes(before, after, &mut data);
+        let body = data.build_with_header(&String::new())?;
+        if !body.is_empty() {
+            lines.push(body);
+        }
+
+        if let Some(s) = prompt_command(shell, &format!("({}) ", profile)) {
+            lines.push(s);
+        }
+
+        Ok(format!("{}\n", lines.join("\n")))
+    }
+
+    fn write_file(path: &Path, data: &str) -> Result<(), String> {
+        match fs::write(path, data) {
+            Ok(_) => Ok(()),
+            Err(e) => Err(format!("{}: {}", e, path.to_string_lossy())),
+        }
+    }
+
+    /// Writes the startup script for `shell` into `dir` and makes `command` load it.
+    fn prepare(
+        shell: &str,
+        dir: &Path,
+        command: &mut Command,
+        before: &[(String, String)],
+        after: &[(String, String)],
+        profile: &str,
+    ) -> Result<(), String> {
+        let user_rc = startup_file(shell, false)?;
+        let user_rc = Some(user_rc.as_str()).filter(|p| Path::new(p).exists());
+
+        match shell {
+            "bash" => {
+                let init = dir.join("bashrc");
+                write_file(&init, &build_init(shell, user_rc, before, after, profile)?)?;
+                command.arg("--rcfile").arg(&init).arg("-i");
+            }
+            "zsh" => {
+                // zsh reads its startup files from $ZDOTDIR, which is restored in .zshrc
+                let mut data = ShellRunCommandFileData::new(shell);
+                match std::env::var("ZDOTDIR") {
+                    Ok(s) => data.add(&"ZDOTDIR".to_string(), &s),
+                    Err(_) => data.unset(&"ZDOTDIR".to_string()),
+                }
+                let init = format!(
+                    "{}\n{}",
+                    data.build_with_header(&String::new())?,
+                    build_init(shell, user_rc, before, after, profile)?
+                );
+
+                let zshenv = format!("{}/.zshenv", zdotdir());
+                let zshenv = format!("{}\n", source_command(shell, &zshenv)?);
+                write_file(&dir.join(".zshenv"), &zshenv)?;
+                write_file(&dir.join(".zshrc"), &init)?;
+                command.env("ZDOTDIR", dir);
+            }
+            // interactive POSIX shells read the file named by $ENV
+            "dash" | "ksh" | "mksh" => {
+                let init = dir.join("init.sh");
+                write_file(&init, &build_init(shell, user_rc, before, after, profile)?)?;
+                command.env("ENV", &init).arg("-i");
+            }
+            // fish and PowerShell load their own startup files before running the command
+            "fish" => {
+                let init = dir.join("init.fish");
+                write_file(&init, &build_init(shell, None, before, after, profile)?)?;
+                command
+                    .arg("-C")
+                    .arg(source_command(shell, &init.to_string_lossy())?);
+            }
+            "powershell" => {
+                let init = dir.join("init.ps1");
+                write_file(&init, &build_init(shell, None, before, after, profile)?)?;
+                command
+                    .arg("-NoExit")
+                    .arg("-Command")
+                    .arg(source_command(shell, &init.to_string_lossy())?);
+            }
+            _ => eprintln!("the prompt marker is not supported for {}.", shell),
+        }
+
+        Ok(())
+    }
+
+    /// Starts an interactive shell with the config applied and waits for it to exit.
+    pub fn shell(settings: &Settings) -> Result<(), String> {
+        let filepath = match (settings.import, settings.operands.first()) {
+            (Some(s), _) => s,
+            (None, Some(s)) => *s,
+            (None, None) => return Err("no config file specified".to_string()),
+        };
+
+        let (shell, _) = resolve_shell(settings.shell_rc.shell)?;
+        let profile = match Path::new(filepath).file_stem() {
+            Some(s) => s.to_string_lossy().to_string(),
+            None => filepath.to_string(),
+        };
+
+        let before = std::env::vars().collect::<Vec<(String, String)>>();
+        let mut after = build_environment(
+            Path::new(filepath),
+            settings.input_format,
+            settings.clean,
+            &settings.keep,
+            &Translation::from_settings(settings)?,
+        )?;
+        after.retain(|(k, _)| k != PROFILE_KEY);
+        after.push((PROFILE_KEY.to_string(), profile.to_string()));
+
+        let dir = utils::utils::create_private_dir("envvar-shell")?;
+
+        let mut command = Command::new(shell_program(shell));
+        command.env_clear().envs(after.iter().cloned());
+
+        let result = prepare(shell, &dir, &mut command, &before, &after, &profile);
+        let status = result.and_then(|_| match command.status() {
+            Ok(s) => Ok(s),
+            Err(e) => Err(format!("{}: {}", e, shell_program(shell))),
+        });
+        let _ = fs::remove_dir_all(&dir);
+
+        match status {
+            Ok(s) => std::process::exit(s.code().unwrap_or(1)),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::subshell::subshell::build_init;
+
+    #[test]
+    fn test_build_init_bash() {
+        let before = vec![("FOO".to_string(), "foo".to_string())];
+        let after = vec![
+            ("FOO".to_string(), "bar".to_string()),
+            ("ENVVAR_PROFILE".to_string(), "staging".to_string()),
+        ];
+
+        let init_result = build_init(
+            "bash",
+            Some("/home/user/.bashrc"),
+            &before,
+            &after,
+            "staging",
+        );
+        assert!(init_result.is_ok());
+
+        let init = init_result.unwrap();
+        let lines = init.lines().collect::<Vec<&str>>();
+        assert_eq!(
+            "if [ -f /home/user/.bashrc ]; then . /home/user/.bashrc; fi",
+            lines[0]
+        );
+        assert!(lines.contains(&"export FOO=bar"));
+        assert!(lines.contains(&"export ENVVAR_PROFILE=staging"));
+        assert_eq!(r#"PS1='(staging) '"${PS1-}""#, *lines.last().unwrap());
+    }
+}
@@ -0,0 +1,471 @@
+pub mod system {
+    use std::fs;
+    use std::io::ErrorKind;
+    use std::path::{Path, PathBuf};
+
+    use crate::capture::capture::{LIST_DELIMITERS, find_extension};
+    use crate::envvar::environment_variable::EnvironmentVariable;
+    use crate::envvar::environment_variable::memory::MemoryEnvironment;
+    use crate::format::format;
+    use crate::json::config;
+    use crate::portable::portable::Translation;
+    use crate::settings::settings::Settings;
+    use crate::shellrc::shellrc::quote_posix;
+
+    /// Read by pam_env at login, without any expansion.
+    pub const ENVIRONMENT: &str = "etc/environment";
+    /// Sourced by login shells, used for what /etc/environment cannot express.
+    pub const PROFILE: &str = "etc/profile.d/envvar.sh";
+
+    const PROFILE_HEADER: &str = "# generated by envvar, do not edit\n";
+
+    /// Splits a line of /etc/environment into its name and value, following pam_env:
+    /// `export` is allowed, and a pair of quotes around the value is removed.
+    fn parse_line(line: &str) -> Option<(String, String)> {
+        let line = line.trim_start();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+
+        let line = line.strip_prefix("export ").unwrap_or(line).trim_start();
+        let (key, value) = line.split_once('=')?;
+        if key.is_empty() || key.contains(char::is_whitespace) {
+            return None;
+        }
+
+        let value = match value.chars().next() {
+            Some(q @ ('"' | '\'')) if value.len() > 1 && value.ends_with(q) => {
+                &value[1..value.len() - 1]
+            }
+            _ => value,
+        };
+        Some((key.to_string(), value.to_string()))
+    }
+
+    /// Joins the lines continued with a trailing backslash.
+    fn logical_lines(content: &str) -> Vec<String> {
+        let mut result: Vec<String> = Vec::new();
+        let mut current = String::new();
+        for line in content.lines() {
+            match line.strip_suffix('\\') {
+                Some(s) => current.push_str(s),
+                None => {
+                    current.push_str(line);
+                    result.push(std::mem::take(&mut current));
+                }
+            }
+        }
+        if !current.is_empty() {
+            result.push(current);
+        }
+
+        result
+    }
+
+    pub fn parse_environment(content: &str) -> Vec<(String, String)> {
+        let mut result: Vec<(String, String)> = Vec::new();
+        for (k, v) in logical_lines(content).iter().filter_map(|l| parse_line(l)) {
+            result.retain(|(rk, _)| *rk != k);
+            result.push((k, v));
+        }
+
+        result
+    }
+
+    /// Formats a line of /etc/environment, or returns `None` for a value it cannot hold.
+    fn format_line(key: &str, value: &str) -> Option<String> {
+        if value.contains(['\n', '\r']) {
+            return None;
+        }
+
+        let needs_quotes = value.contains(char::is_whitespace)
+            || value.starts_with(['"', '\''])
+            || value.ends_with('\\');
+        if needs_quotes {
+            Some(format!("{}=\"{}\"", key, value))
+        } else {
+            Some(format!("{}={}", key, value))
+        }
+    }
+
+    /// Replaces the lines setting the changed variables and appends the new ones, keeping
+    /// comments and the other lines as they are.
+    pub fn update_environment(content: &str, changes: &[(String, String)]) -> String {
+        let mut lines: Vec<String> = Vec::new();
+        let mut written: Vec<&str> = Vec::new();
+
+        for line in logical_lines(content) {
+            let change =
+                parse_line(&line).and_then(|(k, _)| changes.iter().find(|(ck, _)| *ck == k));
+            match change {
+                Some((k, v)) if !written.contains(&k.as_str()) => {
+                    lines.push(format_line(k, v).unwrap_or_default());
+                    written.push(k);
+                }
+                Some(_) => {}
+                None => lines.push(line),
+            }
+        }
+
+        for (k, v) in changes.iter() {
+            if !written.contains(&k.as_str()) {
+                lines.push(format_line(k, v).unwrap_or_default());
+            }
+        }
+
+        lines.iter().map(|l| format!("{}\n", l)).collect()
+    }
+
+    fn read_file(path: &Path) -> Result<String, String> {
+        match fs::read_to_string(path) {
+            Ok(s) => Ok(s),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(String::new()),
+            Err(e) => Err(format!("{}: {}", e, path.to_string_lossy())),
+        }
+    }
+
+    fn write_file(path: &Path, content: &str) -> Result<(), String> {
+        let result = match path.parent() {
+            Some(parent) => fs::create_dir_all(parent).and_then(|_| fs::write(path, content)),
+            None => fs::write(path, content),
+        };
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) if e.kind() == ErrorKind::PermissionDenied => Err(format!(
+                "permission denied: {}\nthe system scope needs root, or --root to write into another directory",
+                path.to_string_lossy()
+            )),
+            Err(e) => Err(format!("{}: {}", e, path.to_string_lossy())),
+        }
+    }
+
+    /// Parses the `export NAME=value` lines of the profile script, keeping the values as
+    /// written.
+    pub fn parse_profile(content: &str) -> Vec<(String, String)> {
+        let mut result: Vec<(String, String)> = Vec::new();
+        for line in content.lines() {
+            if let Some((k, v)) = line.strip_prefix("export ").and_then(|l| l.split_once('=')) {
+                result.retain(|(rk, _)| rk != k);
+                result.push((k.to_string(), v.to_string()));
+            }
+        }
+
+        result
+    }
+
+    /// Removes the quotes of a word written by `quote_posix`.
+    fn unquote_posix(s: &str) -> String {
+        let mut result = String::new();
+        let mut quoted = false;
+        let mut chars = s.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\'' => quoted = !quoted,
+                '\\' if !quoted => result.extend(chars.next()),
+                _ => result.push(c),
+            }
+        }
+
+        result
+    }
+
+    /// Returns the value of an extension written to the profile script, with `$NAME` standing
+    /// for the value at login.
+    fn decode_export(key: &str, value: &str) -> Option<String> {
+        let (prefix, suffix) = value.split_once(&format!("\"${}\"", key))?;
+        Some(format!(
+            "{}${}{}",
+            unquote_posix(prefix),
+            key,
+            unquote_posix(suffix)
+        ))
+    }
+
+    /// The files changed by the system scope.
+    pub struct Plan {
+        pub environment: String,
+        pub profile: String,
+        pub warnings: Vec<String>,
+    }
+
+    /// Applies changes to the variables of /etc/environment and the profile script.
+    pub struct SystemEnvironment {
+        root: PathBuf,
+        defined: Vec<(String, String)>,
+        /// Extensions in the profile script, with the values as written
+        exported: Vec<(String, String)>,
+        base: Vec<(String, String)>,
+        vars: MemoryEnvironment,
+    }
+
+    impl SystemEnvironment {
+        /// Reads /etc/environment and the profile script under `root`. `lists` are the current
+        /// values of the lists to extend that neither file sets.
+        pub fn new(root: &Path, lists: &[(String, String)]) -> Result<Self, String> {
+            let defined = parse_environment(&read_file(&root.join(ENVIRONMENT))?);
+            let exported = parse_profile(&read_file(&root.join(PROFILE))?);
+
+            let mut base = defined.clone();
+            for (k, v) in exported.iter() {
+                if let Some(v) = decode_export(k, v)
+                    && !base.iter().any(|(bk, _)| bk == k)
+                {
+                    base.push((k.to_string(), v));
+                }
+            }
+            for (k, v) in lists.iter() {
+                if !base.iter().any(|(bk, _)| bk == k) {
+                    base.push((k.to_string(), v.to_string()));
+                }
+            }
+
+            Ok(Self {
+                root: root.to_path_buf(),
+                defined,
+                exported,
+                vars: MemoryEnvironment::new(base.clone()),
+                base,
+            })
+        }
+
+        /// Returns the variables set in /etc/environment.
+        pub fn defined(&self) -> &Vec<(String, String)> {
+            &self.defined
+        }
+
+        /// Builds the new content of the files. Values go to /etc/environment, and lists
+        /// extended without being defined there go to the profile script, which refers to
+        /// the value at login. The extensions already in the script are kept.
+        pub fn plan(&self) -> Result<Plan, String> {
+            let mut changes: Vec<(String, String)> = Vec::new();
+            let mut exports: Vec<String> = Vec::new();
+            let mut warnings: Vec<String> = Vec::new();
+            let find = |list: &[(String, String)], key: &str| {
+                list.iter()
+                    .find(|(k, _)| k == key)
+                    .map(|(_, v)| v.to_string())
+            };
+
+            for (k, v) in self.vars.vars().iter() {
+                let prior = find(&self.base, k);
+                let exported = find(&self.exported, k);
+                if prior.as_ref() == Some(v) {
+                    if let Some(e) = exported {
+                        exports.push(format!("export {}={}\n", k, e));
+                    }
+                    continue;
+                }
+
+                let extension = match find(&self.defined, k) {
+                    Some(_) => None,
+                    None => prior.and_then(|p| {
+                        LIST_DELIMITERS
+                            .iter()
+                            .find_map(|d| find_extension(&p, v, d).map(|e| (d, e)))
+                    }),
+                };
+                if let Some((d, (prefix, suffix))) = extension {
+                    let mut value = exported.unwrap_or(format!("\"${}\"", k));
+                    if !prefix.is_empty() {
+                        value = format!("{}{}", quote_posix(&format!("{}{}", prefix, d)), value);
+                    }
+                    if !suffix.is_empty() {
+                        value = format!("{}{}", value, quote_posix(&format!("{}{}", d, suffix)));
+                    }
+                    exports.push(format!("export {}={}\n", k, value));
+                    continue;
+                }
+
+                if format_line(k, v).is_none() {
+                    warnings.push(format!(
+                        "{}: multiline values cannot be expressed in /etc/environment, skipped",
+                        k
+                    ));
+                    continue;
+                }
+                changes.push((k.to_string(), v.to_string()));
+            }
+
+            for (k, _) in self.defined.iter() {
+                if find(self.vars.vars(), k).is_none() {
+                    warnings.push(format!(
+                        "{}: removing a variable from /etc/environment is not supported, skipped",
+                        k
+                    ));
+                }
+            }
+
+            let content = read_file(&self.root.join(ENVIRONMENT))?;
+            Ok(Plan {
+                environment: update_environment(&content, &changes),
+                profile: format!("{}{}", PROFILE_HEADER, exports.concat()),
+                warnings,
+            })
+        }
+
+        pub fn write(&self) -> Result<(), String> {
+            let plan = self.plan()?;
+            for w in plan.warnings.iter() {
+                eprintln!("warning: {}", w);
+            }
+
+            write_file(&self.root.join(ENVIRONMENT), &plan.environment)?;
+            write_file(&self.root.join(PROFILE), &plan.profile)
+        }
+    }
+
+    impl EnvironmentVariable for SystemEnvironment {
+        fn list(&self) -> Result<Vec<(String, String)>, String> {
+            self.vars.list()
+        }
+
+        fn get(&self, name: &String) -> Result<String, String> {
+            self.vars.get(name)
+        }
+
+        fn set(&mut self, name: &String, value: &String) -> Result<(), String> {
+            self.vars.set(name, value)
+        }
+
+        fn delete(&mut self, name: &String) -> Result<(), String> {
+            self.vars.delete(name)
+        }
+
+        fn get_path(&self) -> Result<Vec<String>, String> {
+            self.vars.get_path()
+        }
+
+        fn set_path(&mut self, paths: &Vec<String>) -> Result<(), String> {
+            self.vars.set_path(paths)
+        }
+
+        fn append_path(&mut self, path: &Path) -> Result<(), String> {
+            self.vars.append_path(path)
+        }
+
+        fn insert_path(&mut self, path: &Path, to: usize) -> Result<(), String> {
+            self.vars.insert_path(path, to)
+        }
+
+        fn remove_path(&mut self, from: usize) -> Result<(), String> {
+            self.vars.remove_path(from)
+        }
+
+        fn remove_path_from(&mut self, path: &Path) -> Result<(), String> {
+            self.vars.remove_path_from(path)
+        }
+    }
+
+    fn root(settings: &Settings) -> PathBuf {
+        PathBuf::from(settings.root.unwrap_or("/"))
+    }
+
+    /// Applies --import to /etc/environment and /etc/profile.d/envvar.sh under --root.
+    pub fn import(settings: &Settings) -> Result<(), String> {
+        let filepath = match settings.import {
+            Some(s) => s,
+            None => return Err("no config file specified".to_string()),
+        };
+
+        let config = format::read_config_to_import(
+            Path::new(filepath),
+            settings.input_format,
+            &Translation::from_settings(settings)?,
+        )?;
+        // the lists are extended from their current values, the rest is compared with the files
+        let lists: Vec<(String, String)> = std::env::vars()
+            .filter(|(k, _)| {
+                config
+                    .data
+                    .iter()
+                    .any(|e| &e.key == k && !e.overwrite && !e.delimiter.is_empty())
+            })
+            .collect();
+        let mut system = SystemEnvironment::new(&root(settings), &lists)?;
+        config::apply_config(&config, settings.dry_run, true, &mut system)?;
+        if settings.dry_run {
+            return Ok(());
+        }
+
+        system.write()
+    }
+
+    /// Lists the variables set in /etc/environment under --root.
+    pub fn list(settings: &Settings) -> Result<Vec<(String, String)>, String> {
+        let system = SystemEnvironment::new(&root(settings), &Vec::new())?;
+        Ok(system.defined().clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::envvar::environment_variable::EnvironmentVariable;
+    use crate::system::system::{
+        ENVIRONMENT, PROFILE, SystemEnvironment, parse_environment, update_environment,
+    };
+    use crate::utils::utils::{create_private_dir, vars};
+
+    #[test]
+    fn test_environment_file() {
+        let content = "# comment\nexport LANG=\"C.UTF-8\"\nEDITOR='vim'\nLONG=a\\\nb\n";
+        assert_eq!(
+            vars(&[("LANG", "C.UTF-8"), ("EDITOR", "vim"), ("LONG", "ab")]),
+            parse_environment(content)
+        );
+
+        let changes = vars(&[("EDITOR", "nano -w"), ("NEW", "1")]);
+        assert_eq!(
+            "# comment\nexport LANG=\"C.UTF-8\"\nEDITOR=\"nano -w\"\nLONG=ab\nNEW=1\n",
+            update_environment(content, &changes)
+        );
+    }
+
+    #[test]
+    fn test_write_to_root() {
+        let root = create_private_dir("envvar-system").unwrap();
+        std::fs::create_dir_all(root.join("etc")).unwrap();
+        std::fs::write(root.join(ENVIRONMENT), "LANG=C\n").unwrap();
+        std::fs::create_dir_all(root.join("etc/profile.d")).unwrap();
+        std::fs::write(
+            root.join(PROFILE),
+            "export PATH=\"$PATH\":/opt/a\nexport MANPATH=\"$MANPATH\":/opt/man\n",
+        )
+        .unwrap();
+
+        let lists = vars(&[("PATH", "/usr/bin:/bin")]);
+        let mut system = SystemEnvironment::new(&root, &lists).unwrap();
+        assert_eq!("C", system.get(&"LANG".to_string()).unwrap());
+
+        system
+            .insert_list(
+                &"PATH".to_string(),
+                &"/opt/my app/bin".to_string(),
+                0,
+                &":".to_string(),
+            )
+            .unwrap();
+        system
+            .set(&"LINES".to_string(), &"a\nb".to_string())
+            .unwrap();
+        system
+            .set(&"EDITOR".to_string(), &"vim".to_string())
+            .unwrap();
+        let result = system.write();
+
+        let environment = std::fs::read_to_string(root.join(ENVIRONMENT));
+        let profile = std::fs::read_to_string(root.join(PROFILE));
+        let _ = std::fs::remove_dir_all(&root);
+
+        assert!(result.is_ok(), "{:?}", result);
+        assert_eq!("LANG=C\nEDITOR=vim\n", environment.unwrap());
+        assert_eq!(
+            concat!(
+                "# generated by envvar, do not edit\n",
+                "export PATH='/opt/my app/bin:'\"$PATH\":/opt/a\n",
+                "export MANPATH=\"$MANPATH\":/opt/man\n",
+            ),
+            profile.unwrap()
+        );
+    }
+}
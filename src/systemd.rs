@@ -0,0 +1,452 @@
+pub mod systemd {
+    use std::path::Path;
+
+    use crate::capture::capture::{LIST_DELIMITERS, find_extension};
+    use crate::envvar::environment_variable::EnvironmentVariable;
+    use crate::envvar::environment_variable::memory::MemoryEnvironment;
+    use crate::format::format;
+    use crate::json::config;
+    use crate::portable::portable::Translation;
+    use crate::settings::settings::Settings;
+    use crate::utils;
+
+    /// Targets accepted by `--target`, besides the default `rc`.
+    pub const TARGETS: [&str; 2] = ["environment.d", "environment-file"];
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum Target {
+        /// `environment.d(5)`, read by the systemd user manager and the session
+        EnvironmentD,
+        /// `EnvironmentFile=` of a unit, which expands nothing
+        EnvironmentFile,
+    }
+
+    /// Returns the target named `name`, or `None` for the shell rc file.
+    pub fn resolve_target(name: Option<&str>) -> Result<Option<Target>, String> {
+        match name {
+            None | Some("rc") => Ok(None),
+            Some("environment.d" | "systemd") => Ok(Some(Target::EnvironmentD)),
+            Some("environment-file") => Ok(Some(Target::EnvironmentFile)),
+            Some(s) => Err(format!(
+                "unknown target: {} (supported: rc, {})",
+                s,
+                TARGETS.join(", ")
+            )),
+        }
+    }
+
+    pub fn default_path(target: Target) -> String {
+        let config_home = utils::utils::config_home();
+        match target {
+            Target::EnvironmentD => format!("{}/environment.d/60-envvar.conf", config_home),
+            Target::EnvironmentFile => format!("{}/envvar/envvar.env", config_home),
+        }
+    }
+
+    fn is_valid_key(key: &str) -> bool {
+        !key.is_empty()
+            && !key.starts_with(|c: char| c.is_ascii_digit())
+            && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    }
+
+    /// Quotes `value` for the parser shared by environment.d and `EnvironmentFile=`.
+    /// With `expand`, `$` is left to the expansion of environment.d.
+    pub fn quote(value: &str, expand: bool) -> String {
+        let is_safe = |c: char| c.is_ascii_alphanumeric() || "_./:@%+,-".contains(c);
+        if !value.is_empty() && value.chars().all(is_safe) {
+            return value.to_string();
+        }
+
+        let mut result = String::from("\"");
+        for c in value.chars() {
+            if "\"\\`".contains(c) || (c == '$' && !expand) {
+                result.push('\\');
+            }
+            result.push(c);
+        }
+        result.push('"');
+
+        result
+    }
+
+    /// Removes the quotes of a value written by `quote`, keeping `$` as written.
+    fn unquote(value: &str) -> String {
+        let inner = match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+            Some(v) => v,
+            None => return value.to_string(),
+        };
+
+        let mut result = String::new();
+        let mut chars = inner.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => result.extend(chars.next()),
+                _ => result.push(c),
+            }
+        }
+
+        result
+    }
+
+    /// Parses the `NAME=value` lines of an existing file, keeping the values as written.
+    pub fn parse(content: &str) -> Vec<(String, String)> {
+        let mut result: Vec<(String, String)> = Vec::new();
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+            if let Some((k, v)) = line.split_once('=') {
+                let k = k.trim().to_string();
+                result.retain(|(key, _)| key != &k);
+                result.push((k, v.trim().to_string()));
+            }
+        }
+
+        result
+    }
+
+    /// Keeps track of the variables of the target file and the changes to them, and writes
+    /// them back as an environment.d file or an `EnvironmentFile=`.
+    pub struct SystemdEnvironment {
+        target: Target,
+        /// Variables of the target file, with the values as written
+        defined: Vec<(String, String)>,
+        /// Values before the changes: those of the file, then the lists to extend
+        base: Vec<(String, String)>,
+        vars: MemoryEnvironment,
+    }
+
+    impl SystemdEnvironment {
+        /// `content` is the existing target file, and `lists` the current values of the lists
+        /// to extend that the file does not set.
+        pub fn new(target: Target, content: &str, lists: Vec<(String, String)>) -> Self {
+            let defined = parse(content);
+            let mut base: Vec<(String, String)> = defined
+                .iter()
+                .map(|(k, v)| {
+                    let v = unquote(v);
+                    match target {
+                        Target::EnvironmentD => (k.to_string(), v.replace("$$", "$")),
+                        Target::EnvironmentFile => (k.to_string(), v),
+                    }
+                })
+                .collect();
+            for (k, v) in lists {
+                if !base.iter().any(|(key, _)| key == &k) {
+                    base.push((k, v));
+                }
+            }
+
+            Self {
+                target,
+                vars: MemoryEnvironment::new(base.clone()),
+                defined,
+                base,
+            }
+        }
+
+        /// Builds the file from the variables of the existing file and the changes. In
+        /// environment.d, an extension of a list refers to the value set before, as written in
+        /// the file or with `${NAME}`, and `$$` stands for `$`.
+        /// Returns the content and the warnings about what could not be written.
+        pub fn build(&self) -> (String, Vec<String>) {
+            let mut lines: Vec<String> = Vec::new();
+            let mut warnings: Vec<String> = Vec::new();
+            let find = |list: &[(String, String)], key: &str| {
+                list.iter()
+                    .find(|(k, _)| k == key)
+                    .map(|(_, v)| v.to_string())
+            };
+
+            for (k, v) in self.vars.vars().iter() {
+                let prior = find(&self.base, k);
+                let written = find(&self.defined, k);
+                if prior.as_ref() == Some(v) {
+                    if let Some(w) = written {
+                        lines.push(format!("{}={}", k, w));
+                    }
+                    continue;
+                }
+                if !is_valid_key(k) {
+                    warnings.push(format!("{}: invalid name for systemd, skipped", k));
+                    continue;
+                }
+
+                let value = match self.target {
+                    Target::EnvironmentFile => quote(v, false),
+                    Target::EnvironmentD => {
+                        let extension = prior.and_then(|p| {
+                            LIST_DELIMITERS
+                                .iter()
+                                .find_map(|d| find_extension(&p, v, d).map(|e| (d, e)))
+                        });
+                        let value = match extension {
+                            Some((d, (prefix, suffix))) => {
+                                let reference = match &written {
+                                    Some(w) => unquote(w),
+                                    None => format!("${{{}}}", k),
+                                };
+                                let mut list = vec![reference];
+                                if !prefix.is_empty() {
+                                    list.insert(0, prefix.replace('$', "$$"));
+                                }
+                                if !suffix.is_empty() {
+                                    list.push(suffix.replace('$', "$$"));
+                                }
+                                list.join(d)
+                            }
+                            None => v.replace('$', "$$"),
+                        };
+                        quote(&value, true)
+                    }
+                };
+                lines.push(format!("{}={}", k, value));
+            }
+
+            for (k, _) in self.base.iter() {
+                if find(self.vars.vars(), k).is_none() && find(&self.defined, k).is_none() {
+                    warnings.push(format!(
+                        "{}: removing a variable cannot be expressed for systemd, skipped",
+                        k
+                    ));
+                }
+            }
+
+            (lines.iter().map(|l| format!("{}\n", l)).collect(), warnings)
+        }
+
+        pub fn write(&self, filepath: &Path) -> Result<(), String> {
+            let (content, warnings) = self.build();
+            for w in warnings.iter() {
+                eprintln!("warning: {}", w);
+            }
+
+            if let Some(parent) = filepath.parent()
+                && !parent.as_os_str().is_empty()
+                && let Err(e) = std::fs::create_dir_all(parent)
+            {
+                return Err(format!("{}: {}", e, parent.to_string_lossy()));
+            }
+
+            match std::fs::write(filepath, content) {
+                Ok(_) => Ok(()),
+                Err(e) => Err(format!("{}: {}", e, filepath.to_string_lossy())),
+            }
+        }
+    }
+
+    impl EnvironmentVariable for SystemdEnvironment {
+        fn list(&self) -> Result<Vec<(String, String)>, String> {
+            self.vars.list()
+        }
+
+        fn get(&self, name: &String) -> Result<String, String> {
+            self.vars.get(name)
+        }
+
+        fn set(&mut self, name: &String, value: &String) -> Result<(), String> {
+            self.vars.set(name, value)
+        }
+
+        fn delete(&mut self, name: &String) -> Result<(), String> {
+            self.vars.delete(name)
+        }
+
+        fn get_path(&self) -> Result<Vec<String>, String> {
+            self.vars.get_path()
+        }
+
+        fn set_path(&mut self, paths: &Vec<String>) -> Result<(), String> {
+            self.vars.set_path(paths)
+        }
+
+        fn append_path(&mut self, path: &Path) -> Result<(), String> {
+            self.vars.append_path(path)
+        }
+
+        fn insert_path(&mut self, path: &Path, to: usize) -> Result<(), String> {
+            self.vars.insert_path(path, to)
+        }
+
+        fn remove_path(&mut self, from: usize) -> Result<(), String> {
+            self.vars.remove_path(from)
+        }
+
+        fn remove_path_from(&mut self, path: &Path) -> Result<(), String> {
+            self.vars.remove_path_from(path)
+        }
+    }
+
+    /// Applies --import to the variables of `target`, at --rc or the default path, and
+    /// writes them back.
+    pub fn import(settings: &Settings, target: Target) -> Result<(), String> {
+        let filepath = match settings.import {
+            Some(s) => s,
+            None => return Err("no config file specified".to_string()),
+        };
+
+        let config = format::read_config_to_import(
+            Path::new(filepath),
+            settings.input_format,
+            &Translation::from_settings(settings)?,
+        )?;
+        let output_path = match &settings.shell_rc.output_rc {
+            Some(s) => s.to_string(),
+            None => default_path(target),
+        };
+        let content = match std::fs::read_to_string(&output_path) {
+            Ok(s) => s,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(format!("{}: {}", e, output_path)),
+        };
+
+        // the lists are extended from their current values, the rest is compared with the file
+        let envvar = crate::envvar::environment_variable::env::Environment::new();
+        let lists: Vec<(String, String)> = envvar
+            .list()?
+            .into_iter()
+            .filter(|(k, _)| {
+                config
+                    .data
+                    .iter()
+                    .any(|e| &e.key == k && !e.overwrite && !e.delimiter.is_empty())
+            })
+            .collect();
+        let mut systemd = SystemdEnvironment::new(target, &content, lists);
+        config::apply_config(&config, settings.dry_run, true, &mut systemd)?;
+        if settings.dry_run {
+            return Ok(());
+        }
+
+        if settings.verbose > 0 {
+            println!("{}: {}", TARGETS[target as usize], output_path);
+        }
+
+        systemd.write(Path::new(&output_path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::args::arguments;
+    use crate::envvar::environment_variable::EnvironmentVariable;
+    use crate::systemd::systemd::{SystemdEnvironment, Target, import};
+    use crate::utils::utils::{create_private_dir, vars};
+
+    fn build(target: Target) -> (String, Vec<String>) {
+        let lists = vars(&[("PATH", "/usr/bin:/bin")]);
+        let mut env = SystemdEnvironment::new(target, "KEEP=same\nOLD=x\n", lists);
+        env.insert_list(
+            &"PATH".to_string(),
+            &"/opt/my app/bin".to_string(),
+            0,
+            &":".to_string(),
+        )
+        .unwrap();
+        env.append_list(
+            &"PATH".to_string(),
+            &"/opt/$x".to_string(),
+            &":".to_string(),
+        )
+        .unwrap();
+        env.set(&"KEEP".to_string(), &"same".to_string()).unwrap();
+        env.set(&"PRICE".to_string(), &"\"$5\"".to_string())
+            .unwrap();
+        env.delete(&"OLD".to_string()).unwrap();
+        env.build()
+    }
+
+    #[test]
+    fn test_environment_d() {
+        let (content, warnings) = build(Target::EnvironmentD);
+        assert_eq!(
+            concat!(
+                "KEEP=same\n",
+                "PATH=\"/opt/my app/bin:${PATH}:/opt/$$x\"\n",
+                "PRICE=\"\\\"$$5\\\"\"\n",
+            ),
+            content
+        );
+        assert!(warnings.is_empty(), "{:?}", warnings);
+    }
+
+    #[test]
+    fn test_environment_file() {
+        let (content, _) = build(Target::EnvironmentFile);
+        assert_eq!(
+            concat!(
+                "KEEP=same\n",
+                "PATH=\"/opt/my app/bin:/usr/bin:/bin:/opt/\\$x\"\n",
+                "PRICE=\"\\\"\\$5\\\"\"\n",
+            ),
+            content
+        );
+    }
+
+    #[test]
+    fn test_extend_written() {
+        let content = "PATH=\"/opt/a:${PATH}\"\nPRICE=\"$$5\"\n";
+        let mut env = SystemdEnvironment::new(Target::EnvironmentD, content, Vec::new());
+        env.append_list(&"PATH".to_string(), &"/opt/b".to_string(), &":".to_string())
+            .unwrap();
+        assert_eq!(
+            "PATH=\"/opt/a:${PATH}:/opt/b\"\nPRICE=\"$$5\"\n",
+            env.build().0
+        );
+    }
+
+    #[test]
+    fn test_import_dry_run() {
+        let dir = create_private_dir("envvar-systemd").unwrap();
+        let config = dir.join("c.json");
+        let output = dir.join("60-envvar.conf");
+        std::fs::write(
+            &config,
+            r#"{"version":1,"data":[{"key":"NEW","value":"1"}]}"#,
+        )
+        .unwrap();
+        std::fs::write(&output, "KEEP=1\nOTHER=2\n").unwrap();
+
+        let args = vec![
+            "envvar".to_string(),
+            format!("--import={}", config.to_string_lossy()),
+            format!("--rc={}", output.to_string_lossy()),
+            "--target=environment.d".to_string(),
+            "--dry-run".to_string(),
+        ];
+        let result = import(&arguments::parse(&args).unwrap(), Target::EnvironmentD);
+
+        let content = std::fs::read_to_string(&output);
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert!(result.is_ok(), "{:?}", result);
+        assert_eq!("KEEP=1\nOTHER=2\n", content.unwrap());
+    }
+    #[test]
+    fn test_import_merges_file() {
+        let dir = create_private_dir("envvar-systemd").unwrap();
+        let config = dir.join("c.json");
+        let output = dir.join("60-envvar.conf");
+        std::fs::write(
+            &config,
+            r#"{"version":1,"data":[{"key":"HOME","value":"/x"},{"key":"NEW","value":"1"}]}"#,
+        )
+        .unwrap();
+        std::fs::write(&output, "OTHER=2\nNEW=0\n").unwrap();
+
+        let args = vec![
+            "envvar".to_string(),
+            format!("--import={}", config.to_string_lossy()),
+            format!("--rc={}", output.to_string_lossy()),
+            "--target=environment.d".to_string(),
+        ];
+        let result = import(&arguments::parse(&args).unwrap(), Target::EnvironmentD);
+
+        let content = std::fs::read_to_string(&output);
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert!(result.is_ok(), "{:?}", result);
+        assert_eq!("OTHER=2\nNEW=0\nHOME=/x\n", content.unwrap());
+    }
+}
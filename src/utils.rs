@@ -1,4 +1,8 @@
 pub mod utils {
+    use std::io::Write;
+
+    use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
+
     #[allow(dead_code)]
     pub fn print_hex(s: &Vec<u8>) {
         print!("     | +0 +1 +2 +3 +4 +5 +6 +7  +8 +9 +A +B +C +D +E +F");
@@ -87,9 +91,40 @@ pub mod utils {
     --help              ... display this help text.
     --no-color          ... no color mode.
     --dry-run           ... if this option is specified, the environment variables are not applied.
+    --system            ... target the system-wide variables instead of the user ones.
     --version           ... display version.
     --export=filepath   ... export the environment variable to json file.
     --import=filepath   ... imports a json file and applies it to the environment variable.
+    --clean             ... start from an empty environment keeping only basic variables (exec).
+    --keep=name[,name]  ... variables to keep in addition with --clean.
+    --format=name       ... format of the --export file (json, dotenv, docker, compose, kubernetes, k8s-env), guessed from the file name if omitted.
+    --input-format=name ... format of the --import file (json, dotenv, docker, compose, kubernetes, k8s-env), guessed from the file name if omitted.
+                            with both --import and --export, the file is converted between the formats.
+    --filter=pattern[,pattern]
+                        ... export only the variables matching a pattern (e.g., APP_*).
+    --oci=filepath      ... display or export the Env of an OCI image config or a runtime config.json.
+    --json              ... print the result of diff as json.
+    --sync              ... with --import, also remove what an earlier import set but the config no longer has.
+    --interactive       ... ask which side to take for each conflict of merge.
+    --translate         ... with --import, convert the lists and paths of a config exported on another platform.
+    --platform=name     ... with --export, convert the lists and paths for another platform (posix, windows).
+    --mount-root=dirpath
+                        ... where Windows drives are mounted on posix (default: root of [automount] in /etc/wsl.conf, or /mnt).
+
+    exec -- command     ... run a command with --import applied, without changing any file.
+    diff a b            ... show the variables added, removed and changed from a to b.
+                            a and b are env, oci:filepath, snapshot:name, [format:]filepath, and on linux pid:pid or rc:filepath.
+    snapshot save [name]
+                        ... save a copy of the current environment (named after the time if omitted).
+    snapshot list       ... list the saved snapshots.
+    snapshot show name  ... display the variables of a snapshot.
+    snapshot restore name
+                        ... print a config recreating a snapshot, written to --export or stdout.
+    snapshot delete name
+                        ... delete a snapshot.
+    merge base ours theirs
+                        ... merge two configs changed from base, written to --export or stdout.
+                            conflicts are marked as git does unless --interactive is given.
 "#
         );
 
@@ -100,19 +135,245 @@ pub mod utils {
         print!(
             r#"
 *** linux only ***
-    --shell=name        ... shell name (e.g., bash, zsh, ...), effective only if --export is specified.
-    --rc=filepath       ... output rc filepath (e.g., .envvar_bash).
+    --shell=name        ... shell name (e.g., bash, zsh, ...), detected from $SHELL if omitted.
+    --rc=filepath       ... output rc filepath (default: $XDG_CONFIG_HOME/envvar/<shell>rc).
+    --login             ... target the login startup file instead of the interactive one.
+    --root=dirpath      ... with --system, use /etc under dirpath instead of the root directory.
+    --pid=pid           ... display or export the environment of a running process.
+    --target=name       ... where --import is written: rc (default), environment.d or environment-file.
+                            (default: $XDG_CONFIG_HOME/environment.d/60-envvar.conf, $XDG_CONFIG_HOME/envvar/envvar.env)
+
+    install             ... add a managed block loading the rc file to the shell startup file.
+    uninstall           ... remove the managed block from the shell startup file.
+    activate filepath   ... print commands applying a json file to the current shell session.
+                            (e.g., eval "$(envvar activate filepath)")
+    deactivate          ... print commands restoring the values before the last activate.
+    shell               ... start an interactive shell with --import applied.
+    hook shell          ... print a prompt hook applying .envvar.json of the current directory.
+                            (e.g., eval "$(envvar hook bash)")
+    allow [filepath]    ... trust the current content of .envvar.json.
+    deny [filepath]     ... revoke the trust of .envvar.json.
+    from-rc filepath    ... convert an rc file into json, written to --export or stdout.
+    capture filepath    ... source a script in a clean shell and convert the changes into json.
+    verify-rc           ... check that the rc file generated from --import gives the expected values.
+    watch               ... regenerate the rc file whenever the --import file changes.
 "#
+        );
+
+        #[cfg(target_os = "linux")]
+        println!(
+            "\n    supported shells: {}",
+            crate::shellrc::shellrc::SHELLS.join(", ")
+        );
+    }
+
+    /// Expands a leading `~` to the home directory.
+    pub fn expand_tilde(path: &str) -> String {
+        let home = std::env::var("HOME").unwrap_or_default();
+        if path == "~" {
+            return home;
+        }
+
+        match path.strip_prefix("~/") {
+            Some(rest) => format!("{}/{}", home, rest),
+            None => path.to_string(),
+        }
+    }
+
+    /// Returns `$XDG_CONFIG_HOME`, or `~/.config` when it is not set.
+    pub fn config_home() -> String {
+        match std::env::var("XDG_CONFIG_HOME") {
+            Ok(s) if !s.is_empty() => s,
+            _ => expand_tilde("~/.config"),
+        }
+    }
+
+    /// Returns `$XDG_DATA_HOME`, or `~/.local/share` when it is not set.
+    pub fn data_home() -> String {
+        match std::env::var("XDG_DATA_HOME") {
+            Ok(s) if !s.is_empty() => s,
+            _ => expand_tilde("~/.local/share"),
+        }
+    }
+
+    /// Creates a new directory that only the user can access in the temporary directory,
+    /// named `prefix` with a random suffix. An existing directory is never reused.
+    #[cfg(target_os = "linux")]
+    pub fn create_private_dir(prefix: &str) -> Result<std::path::PathBuf, String> {
+        use std::io::Read;
+        use std::os::unix::fs::DirBuilderExt;
+
+        let mut random = [0u8; 8];
+        let read = std::fs::File::open("/dev/urandom").and_then(|mut f| f.read_exact(&mut random));
+        if let Err(e) = read {
+            return Err(format!("{}: /dev/urandom", e));
+        }
+        let suffix = random.iter().map(|b| format!("{:02x}", b)).collect::<String>();
+
+        let dir = std::env::temp_dir().join(format!("{}-{}", prefix, suffix));
+        match std::fs::DirBuilder::new().mode(0o700).create(&dir) {
+            Ok(_) => Ok(dir),
+            Err(e) => Err(format!("{}: {}", e, dir.to_string_lossy())),
+        }
+    }
+
+    /// Computes a line-based difference between `a` and `b` using the longest common subsequence.
+    /// Each element is tagged with `' '` (common), `'-'` (only in `a`) or `'+'` (only in `b`).
+    pub fn diff<T: PartialEq + Clone>(a: &[T], b: &[T]) -> Vec<(char, T)> {
+        let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+        for i in (0..a.len()).rev() {
+            for j in (0..b.len()).rev() {
+                lcs[i][j] = if a[i] == b[j] {
+                    lcs[i + 1][j + 1] + 1
+                } else {
+                    lcs[i + 1][j].max(lcs[i][j + 1])
+                };
+            }
+        }
+
+        let mut result = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        while i < a.len() && j < b.len() {
+            if a[i] == b[j] {
+                result.push((' ', a[i].clone()));
+                i += 1;
+                j += 1;
+            } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+                result.push(('-', a[i].clone()));
+                i += 1;
+            } else {
+                result.push(('+', b[j].clone()));
+                j += 1;
+            }
+        }
+        result.extend(a[i..].iter().map(|e| ('-', e.clone())));
+        result.extend(b[j..].iter().map(|e| ('+', e.clone())));
+
+        result
+    }
+
+    /// Prints the changed lines of `old` and `new` with a few lines of context.
+    pub fn print_diff(path: &str, old: &str, new: &str, no_color: bool) -> std::io::Result<()> {
+        const CONTEXT: usize = 2;
+
+        let old_lines = old.lines().collect::<Vec<&str>>();
+        let new_lines = new.lines().collect::<Vec<&str>>();
+        let d = diff(&old_lines, &new_lines);
+
+        let choice = if no_color {
+            ColorChoice::Never
+        } else {
+            ColorChoice::Always
+        };
+        let mut stdout = StandardStream::stdout(choice);
+
+        writeln!(&mut stdout, "--- {}", path)?;
+        writeln!(&mut stdout, "+++ {}", path)?;
+        let mut skipped = false;
+        for (i, (tag, line)) in d.iter().enumerate() {
+            let from = i.saturating_sub(CONTEXT);
+            let to = (i + CONTEXT + 1).min(d.len());
+            if !d[from..to].iter().any(|(t, _)| *t != ' ') {
+                skipped = true;
+                continue;
+            }
+
+            if skipped {
+                writeln!(&mut stdout, "...")?;
+                skipped = false;
+            }
+
+            match tag {
+                '-' => stdout.set_color(ColorSpec::new().set_fg(Some(Color::Red)))?,
+                '+' => stdout.set_color(ColorSpec::new().set_fg(Some(Color::Green)))?,
+                _ => stdout.reset()?,
+            }
+            writeln!(&mut stdout, "{}{}", tag, line)?;
+        }
+        stdout.reset()
+    }
+
+    /// Returns the name of this machine, or an empty string if it is unknown.
+    pub fn hostname() -> String {
+        #[cfg(target_os = "linux")]
+        if let Ok(s) = std::fs::read_to_string("/proc/sys/kernel/hostname") {
+            return s.trim().to_string();
+        }
+
+        std::env::var("COMPUTERNAME")
+            .or(std::env::var("HOSTNAME"))
+            .unwrap_or_default()
+    }
+
+    /// Formats seconds since the Unix epoch as an RFC 3339 time in UTC.
+    pub fn format_time(secs: u64) -> String {
+        let days = (secs / 86400) as i64;
+        let rest = secs % 86400;
+
+        // converts days to a civil date (Howard Hinnant's algorithm)
+        let z = days + 719468;
+        let era = z.div_euclid(146097);
+        let doe = z - era * 146097;
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+        format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+            year,
+            month,
+            day,
+            rest / 3600,
+            rest % 3600 / 60,
+            rest % 60
         )
     }
 
+    /// Returns the current time as an RFC 3339 time in UTC.
+    pub fn now() -> String {
+        let secs = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        format_time(secs)
+    }
+
+    /// Builds a list of variables from pairs of `&str`, for tests.
+    #[cfg(test)]
+    pub fn vars(list: &[(&str, &str)]) -> Vec<(String, String)> {
+        list.iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
     pub fn get_name() -> &'static str {
         option_env!("CARGO_PKG_NAME").unwrap_or("???")
     }
 
-
     pub fn get_version() -> &'static str {
-       option_env!("CARGO_PKG_VERSION").unwrap_or("???")
+        option_env!("CARGO_PKG_VERSION").unwrap_or("???")
     }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_create_private_dir() {
+        use std::os::unix::fs::PermissionsExt;
 
+        use crate::utils::utils::create_private_dir;
+
+        let a = create_private_dir("envvar-test").unwrap();
+        let b = create_private_dir("envvar-test").unwrap();
+        let mode = std::fs::metadata(&a).map(|m| m.permissions().mode());
+        let _ = std::fs::remove_dir(&a);
+        let _ = std::fs::remove_dir(&b);
+
+        assert_ne!(a, b);
+        assert_eq!(0o700, mode.unwrap() & 0o777);
+    }
 }
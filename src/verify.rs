@@ -0,0 +1,187 @@
+pub mod verify {
+    use std::fs;
+    use std::path::Path;
+
+    use crate::envvar::environment_variable::memory::MemoryEnvironment;
+    use crate::format::format;
+    use crate::json::config::{self, Config};
+    use crate::portable::portable::Translation;
+    use crate::probe::probe::{SHELL_KEYS, probe};
+    use crate::run::run::clean_environment;
+    use crate::settings::settings::Settings;
+    use crate::shellrc::detect::resolve_shell;
+    use crate::shellrc::shellrc::{ShellRunCommandFile, ShellRunCommandFileData, source_command};
+    use crate::utils;
+
+    /// Builds the run control file that import writes for `env` and `config`, along with
+    /// the environment it is expected to produce.
+    pub fn build_rc(
+        shell: &str,
+        env: &[(String, String)],
+        config: &Config,
+    ) -> Result<(String, Vec<(String, String)>), String> {
+        let mut expected = MemoryEnvironment::new(env.to_vec());
+        config::apply_config(config, false, false, &mut expected)?;
+
+        // as import does, only the changes are written
+        let mut data = ShellRunCommandFileData::new(shell);
+        for (k, v) in env.iter() {
+            data.load(k, v);
+        }
+        for (k, v) in expected.vars().iter() {
+            if data.get(k).as_ref() != Ok(v) {
+                data.add(k, v);
+            }
+        }
+        for (k, _) in env.iter() {
+            if !expected.vars().iter().any(|(ek, _)| ek == k) {
+                data.unset(k);
+            }
+        }
+
+        Ok((data.build()?, expected.vars().clone()))
+    }
+
+    /// Compares the environment a shell ended up with against the expected one.
+    /// Variables the shell set by itself before loading the file are not reported.
+    pub fn compare(
+        expected: &[(String, String)],
+        initial: &[(String, String)],
+        actual: &[(String, String)],
+    ) -> Vec<String> {
+        let mut result: Vec<String> = Vec::new();
+        let find = |list: &[(String, String)], key: &str| {
+            list.iter()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| v.to_string())
+        };
+
+        for (k, v) in expected.iter() {
+            if SHELL_KEYS.contains(&k.as_str()) {
+                continue;
+            }
+
+            match find(actual, k) {
+                Some(a) if a == *v => {}
+                Some(a) => result.push(format!("{}: expected {:?}, got {:?}", k, v, a)),
+                None => result.push(format!("{}: expected {:?}, but not set", k, v)),
+            }
+        }
+
+        for (k, v) in actual.iter() {
+            if find(expected, k).is_none() && find(initial, k).as_ref() != Some(v) {
+                result.push(format!("{}: not expected, got {:?}", k, v));
+            }
+        }
+
+        result
+    }
+
+    /// Sources the run control file for `config` in `shell` started with `env`,
+    /// and returns the variables that do not match.
+    pub fn verify(
+        shell: &str,
+        env: &[(String, String)],
+        config: &Config,
+    ) -> Result<Vec<String>, String> {
+        let (rc, expected) = build_rc(shell, env, config)?;
+
+        let dir = utils::utils::create_private_dir("envvar-verify")?;
+
+        let path = dir.join("rc");
+        let result = match fs::write(&path, rc) {
+            Ok(_) => {
+                source_command(shell, &path.to_string_lossy()).and_then(|c| probe(shell, env, &[c]))
+            }
+            Err(e) => Err(format!("{}: {}", e, path.to_string_lossy())),
+        };
+        let _ = fs::remove_dir_all(&dir);
+
+        let dumps = result?;
+        Ok(compare(&expected, &dumps[0], &dumps[1]))
+    }
+
+    /// Checks that the run control file generated from --import produces the expected environment.
+    pub fn verify_rc(settings: &Settings) -> Result<(), String> {
+        let filepath = match (settings.import, settings.operands.first()) {
+            (Some(s), _) => s,
+            (None, Some(s)) => *s,
+            (None, None) => return Err("no config file specified".to_string()),
+        };
+
+        let (shell, _) = resolve_shell(settings.shell_rc.shell)?;
+        let config = format::read_config_to_import(
+            Path::new(filepath),
+            settings.input_format,
+            &Translation::from_settings(settings)?,
+        )?;
+        let env = clean_environment(&settings.keep);
+
+        let mismatches = verify(shell, &env, &config)?;
+        if mismatches.is_empty() {
+            println!("ok: {}", shell);
+            return Ok(());
+        }
+
+        for m in mismatches.iter() {
+            println!("{}", m);
+        }
+        Err(format!("{}: {} mismatches", shell, mismatches.len()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::json::config::Config;
+    use crate::shellrc::shellrc::{SHELLS, shell_program};
+    use crate::utils::utils::vars;
+    use crate::verify::verify::{compare, verify};
+
+    fn is_installed(program: &str) -> bool {
+        let path = std::env::var("PATH").unwrap_or_default();
+        path.split(':')
+            .any(|d| std::path::Path::new(d).join(program).is_file())
+    }
+
+    #[test]
+    fn test_compare() {
+        let expected = vars(&[("FOO", "foo"), ("BAR", "bar")]);
+        let initial = vars(&[("SHELL_OWN", "1")]);
+        let actual = vars(&[("FOO", "foo"), ("SHELL_OWN", "1"), ("EXTRA", "x")]);
+
+        assert_eq!(
+            vec![
+                r#"BAR: expected "bar", but not set"#,
+                r#"EXTRA: not expected, got "x""#,
+            ],
+            compare(&expected, &initial, &actual)
+        );
+    }
+
+    #[test]
+    fn test_verify_installed_shells() {
+        let json = r#"{"version":1,"data":[
+            {"key":"QUOTES","value":"it's \"quoted\" `cmd` $HOME \\ !event","overwrite":true},
+            {"key":"LINES","value":"first\nsecond","overwrite":true},
+            {"key":"SPACES","value":"  a  b  ","overwrite":true},
+            {"key":"UNICODE","value":"環境変数","overwrite":true},
+            {"key":"PATH","value":"/opt/envvar test/bin","delimiter":":","insert":0}
+        ]}"#;
+        let config = serde_json::from_str::<Config>(json).unwrap();
+        let env = vars(&[
+            ("HOME", "/tmp"),
+            ("PATH", &std::env::var("PATH").unwrap_or_default()),
+        ]);
+
+        for shell in SHELLS.iter().filter(|s| is_installed(&shell_program(s))) {
+            let result = verify(shell, &env, &config);
+            assert!(result.is_ok(), "{}: {:?}", shell, result);
+            assert!(
+                result.as_ref().unwrap().is_empty(),
+                "{}: {:?}",
+                shell,
+                result
+            );
+        }
+    }
+}
@@ -0,0 +1,227 @@
+pub mod watch {
+    use std::ffi::CString;
+    use std::fs;
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+    use std::path::Path;
+
+    use crate::envvar::environment_variable::env::Environment;
+    use crate::portable::portable::Translation;
+    use crate::settings::settings::Settings;
+    use crate::shellrc::detect::resolve_shell;
+    use crate::shellrc::shellrc::default_rc_path;
+    use crate::state::state;
+
+    /// Time without further changes before the rc file is regenerated, in milliseconds.
+    const DEBOUNCE_MS: i32 = 300;
+
+    /// Editors often replace a file instead of writing it, so the directory is watched.
+    const EVENTS: u32 = libc::IN_CLOSE_WRITE
+        | libc::IN_MOVED_TO
+        | libc::IN_MOVED_FROM
+        | libc::IN_CREATE
+        | libc::IN_DELETE;
+
+    /// Splits a buffer read from inotify into the masks and names of the events.
+    pub fn parse_events(buffer: &[u8]) -> Vec<(u32, String)> {
+        const HEADER: usize = std::mem::size_of::<libc::inotify_event>();
+
+        let mut result: Vec<(u32, String)> = Vec::new();
+        let mut offset = 0;
+        while offset + HEADER <= buffer.len() {
+            let event = unsafe {
+                std::ptr::read_unaligned(buffer[offset..].as_ptr() as *const libc::inotify_event)
+            };
+            let start = offset + HEADER;
+            let end = (start + event.len as usize).min(buffer.len());
+            let name = buffer[start..end]
+                .split(|b| *b == 0)
+                .next()
+                .unwrap_or_default();
+
+            result.push((event.mask, String::from_utf8_lossy(name).to_string()));
+            offset = end;
+        }
+
+        result
+    }
+
+    /// Waits for an event on `name`, returning false if nothing happens in `timeout` milliseconds.
+    fn wait_for_change(fd: i32, name: &str, timeout: i32) -> Result<bool, String> {
+        let mut buffer = [0u8; 4096];
+
+        loop {
+            let mut pollfd = libc::pollfd {
+                fd,
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            let n = unsafe { libc::poll(&mut pollfd, 1, timeout) };
+            if n < 0 {
+                let e = std::io::Error::last_os_error();
+                if e.kind() == std::io::ErrorKind::Interrupted {
+                    continue;
+                }
+                return Err(e.to_string());
+            }
+            if n == 0 {
+                return Ok(false);
+            }
+
+            let size =
+                unsafe { libc::read(fd, buffer.as_mut_ptr() as *mut libc::c_void, buffer.len()) };
+            if size < 0 {
+                return Err(std::io::Error::last_os_error().to_string());
+            }
+
+            if parse_events(&buffer[..size as usize])
+                .iter()
+                .any(|(_, n)| n == name)
+            {
+                return Ok(true);
+            }
+        }
+    }
+
+    /// Generates the rc file from the config, replacing the previous one only on success.
+    /// What is set is recorded for `rc_path` as `--import` does, pruned with `sync`.
+    fn generate(
+        shell: &str,
+        config_path: &Path,
+        format: Option<&str>,
+        sync: bool,
+        translation: &Translation,
+        rc_path: &str,
+    ) -> Result<(), String> {
+        let mut envvar = Environment::new();
+        envvar.init_shell(&shell.to_string());
+        let managed = state::import_envvar(
+            config_path,
+            format,
+            rc_path,
+            false,
+            sync,
+            translation,
+            &mut envvar,
+        )?;
+
+        let temporary = format!("{}.tmp", rc_path);
+        envvar.write_rc(Path::new(&temporary))?;
+        if let Err(e) = fs::rename(&temporary, rc_path) {
+            let _ = fs::remove_file(&temporary);
+            return Err(format!("{}: {}", e, rc_path));
+        }
+
+        match managed {
+            Some(m) => state::record(m),
+            None => Ok(()),
+        }
+    }
+
+    fn report(result: Result<(), String>, rc_path: &str) {
+        match result {
+            Ok(_) => println!("updated: {}", rc_path),
+            Err(e) => eprintln!("{}\nkept the last rc: {}", e, rc_path),
+        }
+    }
+
+    /// Regenerates the rc file whenever the config given with --import changes.
+    pub fn watch(settings: &Settings) -> Result<(), String> {
+        let filepath = match settings.import {
+            Some(s) => s,
+            None => return Err("no config file specified".to_string()),
+        };
+        let config_path = match std::path::absolute(filepath) {
+            Ok(p) => p,
+            Err(e) => return Err(e.to_string()),
+        };
+        let (dir, name) = match (config_path.parent(), config_path.file_name()) {
+            (Some(d), Some(n)) => (d, n.to_string_lossy().to_string()),
+            _ => return Err(format!("invalid path: {}", filepath)),
+        };
+
+        let (shell, _) = resolve_shell(settings.shell_rc.shell)?;
+        let translation = Translation::from_settings(settings)?;
+        let rc_path = match &settings.shell_rc.output_rc {
+            Some(s) => s.to_string(),
+            None => default_rc_path(shell),
+        };
+
+        let raw = unsafe { libc::inotify_init1(libc::IN_CLOEXEC) };
+        if raw < 0 {
+            return Err(std::io::Error::last_os_error().to_string());
+        }
+        // closed on every return
+        let inotify = unsafe { OwnedFd::from_raw_fd(raw) };
+        let fd = inotify.as_raw_fd();
+        let c_dir = match CString::new(dir.to_string_lossy().as_bytes()) {
+            Ok(s) => s,
+            Err(e) => return Err(e.to_string()),
+        };
+        if unsafe { libc::inotify_add_watch(fd, c_dir.as_ptr(), EVENTS) } < 0 {
+            let e = std::io::Error::last_os_error();
+            return Err(format!("{}: {}", e, dir.to_string_lossy()));
+        }
+
+        report(
+            generate(
+                shell,
+                &config_path,
+                settings.input_format,
+                settings.sync,
+                &translation,
+                &rc_path,
+            ),
+            &rc_path,
+        );
+        println!("watching: {}", config_path.to_string_lossy());
+
+        loop {
+            wait_for_change(fd, &name, -1)?;
+            // wait until a burst of writes settles
+            while wait_for_change(fd, &name, DEBOUNCE_MS)? {}
+
+            report(
+                generate(
+                    shell,
+                    &config_path,
+                    settings.input_format,
+                    settings.sync,
+                    &translation,
+                    &rc_path,
+                ),
+                &rc_path,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::watch::watch::parse_events;
+
+    fn event(mask: u32, name: &str, len: usize) -> Vec<u8> {
+        let mut result = Vec::new();
+        result.extend(1i32.to_ne_bytes());
+        result.extend(mask.to_ne_bytes());
+        result.extend(0u32.to_ne_bytes());
+        result.extend((len as u32).to_ne_bytes());
+        let mut name = name.as_bytes().to_vec();
+        name.resize(len, 0);
+        result.extend(name);
+        result
+    }
+
+    #[test]
+    fn test_parse_events() {
+        let mut buffer = event(libc::IN_CLOSE_WRITE, "cfg.json", 16);
+        buffer.extend(event(libc::IN_MOVED_TO, ".cfg.json.swp", 16));
+
+        assert_eq!(
+            vec![
+                (libc::IN_CLOSE_WRITE, "cfg.json".to_string()),
+                (libc::IN_MOVED_TO, ".cfg.json.swp".to_string()),
+            ],
+            parse_events(&buffer)
+        );
+    }
+}
@@ -0,0 +1,103 @@
+import os
+import json
+
+import pytest
+
+from tests.it.lib.utils import Utils
+from tests.it.lib.base_test import BaseTest
+
+
+@pytest.mark.skipif(Utils.is_windows(), reason="uses POSIX commands")
+class TestExec(BaseTest):
+    TEST_FILENAME = "test_exec.json"
+
+    def setup_method(self, method: str):
+        with open(TestExec.TEST_FILENAME, "w") as f:
+            json.dump(
+                {
+                    "version": 1,
+                    "data": [{"key": "foo", "value": "baz", "overwrite": True}],
+                },
+                f,
+            )
+        return super().setup_method(method)
+
+    def teardown_method(self, method: str):
+        if os.path.exists(TestExec.TEST_FILENAME):
+            os.remove(TestExec.TEST_FILENAME)
+        return super().teardown_method(method)
+
+    def test_normal(self):
+        COMMAND_LINE = [
+            Utils.MainProgram,
+            "exec",
+            "--import={}".format(TestExec.TEST_FILENAME),
+            "--",
+            "printenv",
+            "foo",
+        ]
+        ENV = {"foo": "bar", "PATH": os.environ["PATH"]}
+
+        expected_return = 0
+        expected_stdout = "baz"
+        expected_stderr = ""
+        Utils.assert_exec_res(
+            COMMAND_LINE,
+            expected_return,
+            expected_stdout,
+            expected_stderr,
+            True,
+            True,
+            True,
+            env=ENV,
+        )
+
+    def test_exit_status(self):
+        COMMAND_LINE = [
+            Utils.MainProgram,
+            "exec",
+            "--import={}".format(TestExec.TEST_FILENAME),
+            "--",
+            "sh",
+            "-c",
+            "'exit 3'",
+        ]
+
+        expected_return = 3
+        expected_stdout = ""
+        expected_stderr = ""
+        Utils.assert_exec_res(
+            COMMAND_LINE,
+            expected_return,
+            expected_stdout,
+            expected_stderr,
+            True,
+            True,
+            True,
+        )
+
+    def test_clean(self):
+        COMMAND_LINE = [
+            Utils.MainProgram,
+            "exec",
+            "--import={}".format(TestExec.TEST_FILENAME),
+            "--clean",
+            "--",
+            "printenv",
+            "hogehoge",
+        ]
+        ENV = {"hogehoge": "piyopiyo", "PATH": os.environ["PATH"]}
+
+        expected_return = 1
+        expected_stdout = ""
+        expected_stderr = ""
+        Utils.assert_exec_res(
+            COMMAND_LINE,
+            expected_return,
+            expected_stdout,
+            expected_stderr,
+            True,
+            True,
+            True,
+            env=ENV,
+        )